@@ -1,7 +1,9 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
 extern crate ckbes;
+use alloc::string::String;
 use core::sync::atomic::Ordering::SeqCst;
 use core::sync::atomic::*;
 
@@ -72,8 +74,9 @@ fn uint_max() {
     assert_eq!(x.load(SeqCst), 0xf731);
 }
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
     bool_compare_exchange();
     bool_and();
     bool_nand();
@@ -83,5 +86,5 @@ pub unsafe extern "C" fn main() -> u64 {
     uint_xor();
     uint_min();
     uint_max();
-    return 0;
+    Ok(())
 }
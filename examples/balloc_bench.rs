@@ -0,0 +1,42 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+extern crate ckbes;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+ckbes::entry!(program_entry);
+
+// Measures the cycles of allocating and freeing many small vectors, the pattern that dominates molecule encoding.
+// Run it at two commits to compare allocator implementations.
+fn program_entry(_: &[String]) -> Result<(), i8> {
+    let n = 4096;
+    let c0 = ckbes::syscall::current_cycles();
+    let mut list: Vec<Vec<u8>> = Vec::with_capacity(n);
+    for i in 0..n {
+        list.push(Vec::with_capacity(16 + i % 48));
+    }
+    let c1 = ckbes::syscall::current_cycles();
+    // Free every other vector first, so that frees cannot merge and the free lists grow long.
+    let mut rest = Vec::with_capacity(n / 2);
+    for (i, e) in list.into_iter().enumerate() {
+        if i % 2 == 0 {
+            drop(e);
+        } else {
+            rest.push(e);
+        }
+    }
+    drop(rest);
+    let c2 = ckbes::syscall::current_cycles();
+    let mut data = Vec::new();
+    for i in 0..n {
+        data.push(i as u8);
+    }
+    let c3 = ckbes::syscall::current_cycles();
+    ckbes::syscall::debug(&format!("alloc {} cycles", c1 - c0));
+    ckbes::syscall::debug(&format!("free {} cycles", c2 - c1));
+    ckbes::syscall::debug(&format!("grow {} cycles", c3 - c2));
+    Ok(())
+}
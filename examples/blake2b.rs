@@ -1,10 +1,13 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
 extern crate ckbes;
+use alloc::string::String;
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
     let pubkey = [
         0x02, 0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87, 0x0b, 0x07, 0x02,
         0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b, 0x16, 0xf8, 0x17, 0x98,
@@ -15,5 +18,5 @@ pub unsafe extern "C" fn main() -> u64 {
     ];
     let result = ckbes::blake2b::blake2b_160(&pubkey);
     assert_eq!(expect, result);
-    return 0;
+    Ok(())
 }
@@ -1,12 +1,13 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
 extern crate ckbes;
+use alloc::string::String;
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
-    #[allow(static_mut_refs)]
-    let data = unsafe { ckbes::global::ARGS.join(" ") };
-    ckbes::syscall::debug(&data);
-    return 0;
+ckbes::entry!(program_entry);
+
+fn program_entry(args: &[String]) -> Result<(), i8> {
+    ckbes::syscall::debug(&args.join(" "));
+    Ok(())
 }
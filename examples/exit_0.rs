@@ -1,9 +1,12 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
 extern crate ckbes;
+use alloc::string::String;
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
-    return 0;
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
+    Ok(())
 }
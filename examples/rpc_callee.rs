@@ -0,0 +1,23 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+extern crate ckbes;
+use alloc::string::String;
+
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
+    let mut fds = ckbes::process::inherited_fds().unwrap();
+    let writer = fds.pop().unwrap();
+    let reader = fds.pop().unwrap();
+    let mut server = ckbes::channel::Server::new(reader, writer);
+    server
+        .serve(|method, payload| match method {
+            1 => Ok(ckbes::blake2b::blake2b_256(payload).to_vec()),
+            2 => Ok(ckbes::blake2b::blake2b_160(payload).to_vec()),
+            _ => Err(1),
+        })
+        .unwrap();
+    Ok(())
+}
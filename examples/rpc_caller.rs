@@ -0,0 +1,22 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+extern crate ckbes;
+use alloc::string::String;
+
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
+    let mut child =
+        ckbes::process::Command::new(1, ckbes::core::Source::CellDep).stdin(true).stdout(true).spawn().unwrap();
+    let mut client = ckbes::channel::Client::new(child.stdout.take().unwrap(), child.stdin.take().unwrap());
+    let hash = client.call(1, b"Hello World!").unwrap();
+    assert_eq!(hash, ckbes::blake2b::blake2b_256(b"Hello World!"));
+    let hash = client.call(2, b"Hello World!").unwrap();
+    assert_eq!(hash, ckbes::blake2b::blake2b_160(b"Hello World!"));
+    assert_eq!(client.call(3, b""), Err(ckbes::channel::CallError::Remote(1)));
+    drop(client);
+    assert_eq!(child.wait().unwrap(), 0);
+    Ok(())
+}
@@ -1,13 +1,16 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
 extern crate ckbes;
+use alloc::string::String;
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
     let tx = ckbes::syscall::load_tx();
     let sighash_all = tx.hash_sighash_all();
     let witness_arg = ckbes::core::WitnessArgs::molecule_decode(&tx.witnesses[0]);
     assert_eq!(witness_arg.lock.unwrap(), sighash_all);
-    return 0;
+    Ok(())
 }
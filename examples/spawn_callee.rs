@@ -1,14 +1,16 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
 extern crate ckbes;
+use alloc::string::String;
+use ckbes::io::Write;
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
-    let fds = ckbes::syscall::inherited_fds();
-    #[allow(static_mut_refs)]
-    let out = unsafe { ckbes::global::ARGS.join(" ") };
-    ckbes::syscall::write(fds[0], out.as_bytes());
-    ckbes::syscall::close(fds[0]);
-    return 0;
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
+    let mut fds = ckbes::process::inherited_fds()?;
+    let args: alloc::vec::Vec<&[u8]> = ckbes::env::args_bytes().collect();
+    fds[0].write_all(&args.join(&b' '))?;
+    Ok(())
 }
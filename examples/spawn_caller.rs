@@ -1,15 +1,23 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
 extern crate ckbes;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ckbes::io::Read;
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
-    let argv = ["Hello", "World!"];
-    let fds = ckbes::syscall::pipe();
-    let pid = ckbes::syscall::spawn(1, ckbes::core::SOURCE_CELL_DEP, &argv, &[fds[1]]);
-    let data = ckbes::syscall::read_all(fds[0]);
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
+    let mut child = ckbes::process::Command::new(1, ckbes::core::Source::CellDep)
+        .args(&["Hello", "World!"])
+        .stdout(true)
+        .spawn()
+        .unwrap();
+    let mut data = Vec::new();
+    child.stdout.as_mut().unwrap().read_to_end(&mut data).unwrap();
     assert_eq!(&data, b"Hello World!");
-    ckbes::syscall::wait(pid);
-    return 0;
+    assert_eq!(child.wait().unwrap(), 0);
+    Ok(())
 }
@@ -4,19 +4,21 @@
 extern crate alloc;
 extern crate ckbes;
 use alloc::format;
+use alloc::string::String;
 
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn main() -> u64 {
+ckbes::entry!(program_entry);
+
+fn program_entry(_: &[String]) -> Result<(), i8> {
     let current_cycles = ckbes::syscall::current_cycles();
     ckbes::syscall::debug(&format!("{:?}", current_cycles));
 
-    let cell = ckbes::syscall::load_cell(0, ckbes::core::SOURCE_INPUT);
+    let cell = ckbes::syscall::load_cell(0, ckbes::core::Source::Input);
     ckbes::syscall::debug(&format!("{:?}", cell));
 
-    let cell_data = ckbes::syscall::load_cell_data(0, ckbes::core::SOURCE_INPUT);
+    let cell_data = ckbes::syscall::load_cell_data(0, ckbes::core::Source::Input);
     ckbes::syscall::debug(&format!("{:?}", cell_data));
 
-    let input = ckbes::syscall::load_input(0, ckbes::core::SOURCE_INPUT);
+    let input = ckbes::syscall::load_input(0, ckbes::core::Source::Input);
     ckbes::syscall::debug(&format!("{:?}", input));
 
     let script_hash = ckbes::syscall::load_script_hash();
@@ -34,5 +36,5 @@ pub unsafe extern "C" fn main() -> u64 {
     let vm_version = ckbes::syscall::vm_version();
     ckbes::syscall::debug(&format!("{:?}", vm_version));
 
-    return 0;
+    Ok(())
 }
@@ -0,0 +1,278 @@
+//! Typed arguments for exec and spawn.
+//!
+//! Arguments are nul terminated strings, so binary values must be encoded. An encoded argument is the name of its type,
+//! a colon and the hex of its molecule serialization, for example "u32:2a000000". The child parses its arguments in the
+//! order the parent passed them, and a value of the wrong type is reported as wrong format instead of being
+//! misread.
+//!
+//! ```ignore
+//! // Parent.
+//! let child = ckbes::process::Command::new(1, Source::CellDep).arg_typed(&script.hash()).arg_typed(&100u64).spawn()?;
+//! // Child.
+//! let mut parser = ckbes::args::parser();
+//! let hash: [u8; 32] = parser.parse()?;
+//! let amount: u64 = parser.parse()?;
+//! parser.finish()?;
+//! ```
+
+use crate::core::{OutPoint, Script};
+use crate::syscall::SysError;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A value that can be passed as a script argument.
+pub trait Arg: Sized {
+    /// The type name written in front of the encoded value.
+    const NAME: &'static str;
+    fn molecule(&self) -> Vec<u8>;
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError>;
+}
+
+impl Arg for u8 {
+    const NAME: &'static str = "u8";
+    fn molecule(&self) -> Vec<u8> {
+        crate::molecule::encode_byte(*self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        Ok(crate::molecule::decode_byte(check_size(data, 1)?))
+    }
+}
+
+impl Arg for u32 {
+    const NAME: &'static str = "u32";
+    fn molecule(&self) -> Vec<u8> {
+        crate::molecule::encode_u32(*self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        Ok(crate::molecule::decode_u32(check_size(data, 4)?))
+    }
+}
+
+impl Arg for u64 {
+    const NAME: &'static str = "u64";
+    fn molecule(&self) -> Vec<u8> {
+        crate::molecule::encode_u64(*self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        Ok(crate::molecule::decode_u64(check_size(data, 8)?))
+    }
+}
+
+impl Arg for u128 {
+    const NAME: &'static str = "u128";
+    fn molecule(&self) -> Vec<u8> {
+        crate::molecule::encode_u128(*self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        Ok(crate::molecule::decode_u128(check_size(data, 16)?))
+    }
+}
+
+impl Arg for [u8; 32] {
+    const NAME: &'static str = "byte32";
+    fn molecule(&self) -> Vec<u8> {
+        crate::molecule::encode_byte32(*self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        Ok(crate::molecule::decode_byte32(check_size(data, 32)?))
+    }
+}
+
+impl Arg for Vec<u8> {
+    const NAME: &'static str = "bytes";
+    fn molecule(&self) -> Vec<u8> {
+        crate::molecule::encode_bytes(self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        if data.len() < 4 || u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize != data.len() - 4 {
+            return Err(SysError::WrongFormat);
+        }
+        Ok(crate::molecule::decode_bytes(data))
+    }
+}
+
+impl Arg for Script {
+    const NAME: &'static str = "script";
+    fn molecule(&self) -> Vec<u8> {
+        Script::molecule(self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        let head = check_table(data, 3)?;
+        if head[1] - head[0] != 32 || head[2] - head[1] != 1 {
+            return Err(SysError::WrongFormat);
+        }
+        Vec::<u8>::molecule_decode(&data[head[2]..])?;
+        Ok(Script::molecule_decode(data))
+    }
+}
+
+impl Arg for OutPoint {
+    const NAME: &'static str = "out_point";
+    fn molecule(&self) -> Vec<u8> {
+        OutPoint::molecule(self)
+    }
+    fn molecule_decode(data: &[u8]) -> Result<Self, SysError> {
+        Ok(OutPoint::molecule_decode(check_size(data, OutPoint::molecule_size())?))
+    }
+}
+
+fn check_size(data: &[u8], size: usize) -> Result<&[u8], SysError> {
+    if data.len() != size {
+        return Err(SysError::WrongFormat);
+    }
+    Ok(data)
+}
+
+// Checks the header of a molecule table with the given number of fields and returns the field offsets.
+pub(crate) fn check_table(data: &[u8], fields: usize) -> Result<Vec<usize>, SysError> {
+    let word = |i: usize| u32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap()) as usize;
+    if data.len() < 4 + fields * 4 || word(0) != data.len() || word(1) != 4 + fields * 4 {
+        return Err(SysError::WrongFormat);
+    }
+    let mut head: Vec<usize> = (0..fields).map(|i| word(i + 1)).collect();
+    head.push(data.len());
+    if head.windows(2).any(|e| e[0] > e[1]) {
+        return Err(SysError::WrongFormat);
+    }
+    Ok(head)
+}
+
+/// Encodes a value as a script argument.
+pub fn encode<T: Arg>(value: &T) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut r = String::from(T::NAME);
+    r.push(':');
+    for e in value.molecule() {
+        r.push(HEX[(e >> 4) as usize] as char);
+        r.push(HEX[(e & 0xf) as usize] as char);
+    }
+    r
+}
+
+/// Decodes a script argument produced by encode. Reports wrong format if the argument is malformed or holds another
+/// type.
+pub fn decode<T: Arg>(arg: &[u8]) -> Result<T, SysError> {
+    let body = arg.strip_prefix(T::NAME.as_bytes()).and_then(|e| e.strip_prefix(b":")).ok_or(SysError::WrongFormat)?;
+    if body.len() % 2 != 0 {
+        return Err(SysError::WrongFormat);
+    }
+    let nibble = |c: u8| match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        _ => Err(SysError::WrongFormat),
+    };
+    let mut data = Vec::with_capacity(body.len() / 2);
+    for e in body.chunks(2) {
+        data.push(nibble(e[0])? << 4 | nibble(e[1])?);
+    }
+    T::molecule_decode(&data)
+}
+
+/// Parses typed arguments in order.
+pub struct Parser<I> {
+    iter: I,
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]>> Parser<I> {
+    pub fn new(iter: I) -> Self {
+        Self { iter }
+    }
+
+    /// Parses the next argument. Reports item missing if there are no arguments left.
+    pub fn parse<T: Arg>(&mut self) -> Result<T, SysError> {
+        decode(self.iter.next().ok_or(SysError::ItemMissing)?)
+    }
+
+    /// Reports wrong format if any argument is left unparsed.
+    pub fn finish(mut self) -> Result<(), SysError> {
+        match self.iter.next() {
+            Some(_) => Err(SysError::WrongFormat),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns a parser over the arguments of the current script.
+pub fn parser() -> Parser<impl Iterator<Item = &'static [u8]>> {
+    Parser::new(crate::env::args_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn round_trip<T: Arg + PartialEq + core::fmt::Debug>(value: T) {
+        assert_eq!(decode::<T>(encode(&value).as_bytes()), Ok(value));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(0xabu8);
+        round_trip(0xdeadbeefu32);
+        round_trip(u64::MAX - 1);
+        round_trip(1u128 << 100);
+        round_trip([7u8; 32]);
+        round_trip(Vec::<u8>::new());
+        round_trip(vec![0u8, 1, 2, 0xff]);
+        round_trip(Script::new([1; 32], crate::core::SCRIPT_HASH_TYPE_TYPE, vec![]));
+        round_trip(Script::new([2; 32], crate::core::SCRIPT_HASH_TYPE_DATA1, vec![3; 20]));
+        round_trip(OutPoint::new([4; 32], 5));
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(&42u32), "u32:2a000000");
+        assert_eq!(encode(&vec![0xabu8]), "bytes:01000000ab");
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        assert_eq!(decode::<u64>(encode(&42u32).as_bytes()), Err(SysError::WrongFormat));
+        assert_eq!(decode::<u32>(b"u32"), Err(SysError::WrongFormat));
+        assert_eq!(decode::<u32>(b"2a000000"), Err(SysError::WrongFormat));
+        assert_eq!(decode::<u32>(b"u64:2a000000"), Err(SysError::WrongFormat));
+    }
+
+    #[test]
+    fn test_malformed_hex() {
+        assert_eq!(decode::<u32>(b"u32:2a00000"), Err(SysError::WrongFormat));
+        assert_eq!(decode::<u32>(b"u32:2A000000"), Err(SysError::WrongFormat));
+        assert_eq!(decode::<u32>(b"u32:2g000000"), Err(SysError::WrongFormat));
+        assert_eq!(decode::<u32>(b"u32:2a0000"), Err(SysError::WrongFormat));
+        assert_eq!(decode::<Vec<u8>>(b"bytes:02000000ab"), Err(SysError::WrongFormat));
+    }
+
+    #[test]
+    fn test_malformed_table() {
+        let script = Script::new([1; 32], 1, vec![2]).molecule();
+        // Total size larger than the data.
+        let mut data = script.clone();
+        data[0] += 1;
+        assert_eq!(<Script as Arg>::molecule_decode(&data), Err(SysError::WrongFormat));
+        // Wrong number of fields.
+        let mut data = script.clone();
+        data[4] = 8;
+        assert_eq!(<Script as Arg>::molecule_decode(&data), Err(SysError::WrongFormat));
+        // Offsets going backwards.
+        let mut data = script.clone();
+        data[8] = 0xff;
+        assert_eq!(<Script as Arg>::molecule_decode(&data), Err(SysError::WrongFormat));
+        // Truncated header.
+        assert_eq!(<Script as Arg>::molecule_decode(&script[..6]), Err(SysError::WrongFormat));
+        assert_eq!(check_table(&[], 2), Err(SysError::WrongFormat));
+        assert_eq!(check_table(&script, 3), Ok(vec![16, 48, 49, script.len()]));
+    }
+
+    #[test]
+    fn test_parser() {
+        let args = [encode(&1u8), encode(&OutPoint::new([0; 32], 1)), encode(&2u64)];
+        let mut parser = Parser::new(args.iter().map(|e| e.as_bytes()));
+        assert_eq!(parser.parse::<u8>(), Ok(1));
+        assert_eq!(parser.parse::<OutPoint>(), Ok(OutPoint::new([0; 32], 1)));
+        assert_eq!(Parser::new(args.iter().map(|e| e.as_bytes())).finish(), Err(SysError::WrongFormat));
+        assert_eq!(parser.parse::<u64>(), Ok(2));
+        assert_eq!(parser.parse::<u64>(), Err(SysError::ItemMissing));
+        assert_eq!(parser.finish(), Ok(()));
+    }
+}
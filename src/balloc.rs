@@ -4,36 +4,67 @@
 //! The buddy allocator works by recursively splitting memory blocks into pairs of equal-sized "buddies" until it finds
 //! a block of the appropriate size. When memory is freed, it attempts to merge adjacent buddy blocks back together to
 //! reduce fragmentation.
+//!
+//! Each order keeps its free blocks in a doubly linked list, whose links are stored in the first two words of the
+//! free blocks, and a bitmap telling whether a block is free at that order. Finding the buddy of a block and taking it
+//! out of its list are constant time, so allocation and free cost O(MAX_ORDER).
+//!
+//! The pool is 1 MiB with 64 byte minimum blocks by default. Both can be changed at build time with the
+//! CKBES_HEAP_SIZE and CKBES_MIN_BLOCK environment variables, for example in the [env] section of .cargo/config.toml.
+//! They must be powers of two, and the pool must leave room for code and stack in the 4 MiB of vm memory.
+//!
+//! A block is aligned to its own size relative to the pool, and the pool is aligned to POOL_ALIGN, so every block is
+//! aligned to the smaller of its size and POOL_ALIGN. Layouts asking for a larger alignment are not satisfied.
 
 use core::alloc::{GlobalAlloc, Layout};
 use core::cmp::min;
 use core::ptr::addr_of_mut;
 
-pub const MIN_BLOCK: usize = 64;
-pub const MAX_ORDER: usize = 14;
-pub const MAX_TOTAL: usize = MIN_BLOCK * (1 << MAX_ORDER);
+pub const MIN_BLOCK: usize = parse_env(option_env!("CKBES_MIN_BLOCK"), 64);
+pub const MAX_TOTAL: usize = parse_env(option_env!("CKBES_HEAP_SIZE"), 1024 * 1024);
+pub const MAX_ORDER: usize = (MAX_TOTAL.trailing_zeros() - MIN_BLOCK.trailing_zeros()) as usize;
+/// The memory of the ckb-vm.
+pub const VM_MEMORY: usize = 4 * 1024 * 1024;
+const _: () = {
+    assert!(MIN_BLOCK.is_power_of_two(), "CKBES_MIN_BLOCK must be a power of two");
+    assert!(MIN_BLOCK >= 2 * core::mem::size_of::<usize>(), "CKBES_MIN_BLOCK must hold two pointers");
+    assert!(MAX_TOTAL.is_power_of_two(), "CKBES_HEAP_SIZE must be a power of two");
+    assert!(MAX_TOTAL >= MIN_BLOCK, "CKBES_HEAP_SIZE must not be smaller than CKBES_MIN_BLOCK");
+    assert!(MAX_TOTAL < VM_MEMORY, "CKBES_HEAP_SIZE must be smaller than the vm memory");
+    assert!(core::mem::align_of::<Pool>() == POOL_ALIGN);
+};
+/// The alignment of the pool, which is the largest alignment the allocator guarantees.
+pub const POOL_ALIGN: usize = 4096;
 pub const PTR_ALLOC: *mut u8 = addr_of_mut!(PRE_ALLOC) as *mut u8;
 pub static mut FREE_LIST: [usize; MAX_ORDER + 1] = {
     let mut list = [usize::MAX; MAX_ORDER + 1];
     list[MAX_ORDER] = 0;
     list
 };
-pub static mut PRE_ALLOC: [u8; MAX_TOTAL] = {
+/// Free block bitmaps of all orders, one bit per block. The bits of an order start at bitmap_base(order).
+pub const BITMAP_BITS: usize = 2 << MAX_ORDER;
+pub static mut FREE_BITMAP: [usize; BITMAP_BITS.div_ceil(usize::BITS as usize)] = {
+    let mut bitmap = [0; BITMAP_BITS.div_ceil(usize::BITS as usize)];
+    let bit = bitmap_base(MAX_ORDER);
+    bitmap[bit / usize::BITS as usize] = 1 << (bit % usize::BITS as usize);
+    bitmap
+};
+/// Bytes held by allocated blocks, and the highest value it has reached.
+pub static mut USED: usize = 0;
+pub static mut PEAK: usize = 0;
+/// The memory pool, aligned to POOL_ALIGN.
+#[repr(C, align(4096))]
+pub struct Pool(pub [u8; MAX_TOTAL]);
+pub static mut PRE_ALLOC: Pool = {
     assert!(matches!(usize::BITS, 32 | 64));
+    // The whole pool is one free block with no previous and no next block.
     let mut alloc = [0; MAX_TOTAL];
-    if usize::BITS >= 32 {
-        alloc[0] = 0xff;
-        alloc[1] = 0xff;
-        alloc[2] = 0xff;
-        alloc[3] = 0xff;
-    }
-    if usize::BITS >= 64 {
-        alloc[4] = 0xff;
-        alloc[5] = 0xff;
-        alloc[6] = 0xff;
-        alloc[7] = 0xff;
-    }
-    alloc
+    let mut i = 0;
+    while i < 2 * core::mem::size_of::<usize>() {
+        alloc[i] = 0xff;
+        i += 1;
+    }
+    Pool(alloc)
 };
 
 /// Buddy allocation algorithm implementation.
@@ -57,8 +88,7 @@ impl Algorithm {
             let block_size = MIN_BLOCK << order;
             if FREE_LIST[order] != usize::MAX {
                 let block_offset = FREE_LIST[order];
-                let block_ptr = PTR_ALLOC.add(block_offset);
-                FREE_LIST[order] = uldr(block_ptr);
+                Algorithm::remove(block_offset, order);
                 return Blockinfo { offset: block_offset, length: block_size };
             }
             let block = Algorithm::alloc(order + 1);
@@ -66,10 +96,7 @@ impl Algorithm {
             if block_offset == usize::MAX {
                 return Blockinfo { offset: block_offset, length: 0 };
             }
-            let buddy_offset = block_offset + block_size;
-            let buddy_ptr = PTR_ALLOC.add(buddy_offset);
-            ustr(buddy_ptr, usize::MAX);
-            FREE_LIST[order] = buddy_offset;
+            Algorithm::insert(block_offset + block_size, order);
             Blockinfo { offset: block_offset, length: block_size }
         }
     }
@@ -84,47 +111,105 @@ impl Algorithm {
                         break;
                     }
                     s += MIN_BLOCK << order;
-                    n = uldr(PTR_ALLOC.add(n));
+                    n = uldr(PTR_ALLOC.add(n), 1);
                 }
             }
             s
         }
     }
 
-    pub fn close(block: Blockinfo) {
+    /// Returns whether the block at offset is free at exactly this order.
+    pub fn is_free(offset: usize, order: usize) -> bool {
         unsafe {
-            if block.offset == usize::MAX {
-                return;
-            }
-            let order = log2(MIN_BLOCK, block.length);
-            let block_idx = block.offset / block.length;
-            let buddy_idx = block_idx ^ 1;
-            let buddy_offset = buddy_idx * block.length;
-            let buddy = Blockinfo { offset: buddy_offset, length: block.length };
-            let upper = Blockinfo { offset: min(block.offset, buddy_offset), length: block.length << 1 };
-            let mut n = FREE_LIST[order];
-            let mut m: usize;
-            loop {
-                if n == usize::MAX {
-                    let block_ptr = PTR_ALLOC.add(block.offset);
-                    ustr(block_ptr, FREE_LIST[order]);
-                    FREE_LIST[order] = block.offset;
-                    break;
-                }
-                m = uldr(PTR_ALLOC.add(n));
-                if n == buddy.offset {
-                    FREE_LIST[order] = m;
-                    Algorithm::close(upper);
-                    break;
-                }
-                if m == buddy.offset {
-                    ustr(PTR_ALLOC.add(n), uldr(PTR_ALLOC.add(m)));
-                    Algorithm::close(upper);
-                    break;
-                }
-                n = m;
+            let bit = bitmap_base(order) + offset / (MIN_BLOCK << order);
+            FREE_BITMAP[bit / usize::BITS as usize] & (1 << (bit % usize::BITS as usize)) != 0
+        }
+    }
+
+    /// Puts the block at offset at the head of the free list of this order.
+    pub fn insert(offset: usize, order: usize) {
+        unsafe {
+            let head = FREE_LIST[order];
+            ustr(PTR_ALLOC.add(offset), 0, usize::MAX);
+            ustr(PTR_ALLOC.add(offset), 1, head);
+            if head != usize::MAX {
+                ustr(PTR_ALLOC.add(head), 0, offset);
+            }
+            FREE_LIST[order] = offset;
+            Algorithm::flip(offset, order);
+        }
+    }
+
+    /// Takes the free block at offset out of the free list of this order.
+    pub fn remove(offset: usize, order: usize) {
+        unsafe {
+            let prev = uldr(PTR_ALLOC.add(offset), 0);
+            let next = uldr(PTR_ALLOC.add(offset), 1);
+            if prev == usize::MAX {
+                FREE_LIST[order] = next;
+            } else {
+                ustr(PTR_ALLOC.add(prev), 1, next);
+            }
+            if next != usize::MAX {
+                ustr(PTR_ALLOC.add(next), 0, prev);
+            }
+            Algorithm::flip(offset, order);
+        }
+    }
+
+    fn flip(offset: usize, order: usize) {
+        unsafe {
+            let bit = bitmap_base(order) + offset / (MIN_BLOCK << order);
+            FREE_BITMAP[bit / usize::BITS as usize] ^= 1 << (bit % usize::BITS as usize);
+        }
+    }
+
+    /// Resizes an allocated block in place, returning whether it succeeded. Shrinking always succeeds and frees the
+    /// upper halves. Growing succeeds when the block is the lower buddy at each order on the way up and every upper
+    /// buddy is free.
+    pub fn resize(offset: usize, order: usize, new_order: usize) -> bool {
+        if new_order > MAX_ORDER {
+            return false;
+        }
+        if new_order < order {
+            for o in (new_order..order).rev() {
+                Algorithm::close(Blockinfo { offset: offset + (MIN_BLOCK << o), length: MIN_BLOCK << o });
+            }
+            return true;
+        }
+        for o in order..new_order {
+            if !offset.is_multiple_of(MIN_BLOCK << (o + 1)) || !Algorithm::is_free(offset + (MIN_BLOCK << o), o) {
+                return false;
             }
         }
+        for o in order..new_order {
+            Algorithm::remove(offset + (MIN_BLOCK << o), o);
+        }
+        true
+    }
+
+    /// The largest number of bytes held by allocated blocks at any time.
+    pub fn peak() -> usize {
+        unsafe { PEAK }
+    }
+
+    pub fn close(block: Blockinfo) {
+        if block.offset == usize::MAX {
+            return;
+        }
+        let mut order = log2(MIN_BLOCK, block.length);
+        let mut offset = block.offset;
+        // Merge with the buddy as long as it is free, then free the merged block.
+        while order < MAX_ORDER {
+            let buddy_offset = offset ^ (MIN_BLOCK << order);
+            if !Algorithm::is_free(buddy_offset, order) {
+                break;
+            }
+            Algorithm::remove(buddy_offset, order);
+            offset = min(offset, buddy_offset);
+            order += 1;
+        }
+        Algorithm::insert(offset, order);
     }
 }
 
@@ -134,26 +219,76 @@ pub struct Allocator {}
 unsafe impl GlobalAlloc for Allocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         unsafe {
-            let order = log2(MIN_BLOCK, clp2(layout.size()).max(MIN_BLOCK));
-            let block = Algorithm::alloc(order);
+            if layout.align() > POOL_ALIGN {
+                return core::ptr::null_mut();
+            }
+            let block = Algorithm::alloc(layout_order(layout));
             if block.offset == usize::MAX {
                 return core::ptr::null_mut();
             }
+            USED += block.length;
+            PEAK = PEAK.max(USED);
             PTR_ALLOC.add(block.offset)
         }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         unsafe {
-            let order = log2(MIN_BLOCK, clp2(layout.size()).max(MIN_BLOCK));
+            let order = layout_order(layout);
             let block = Blockinfo { offset: ptr.offset_from(PTR_ALLOC) as usize, length: MIN_BLOCK << order };
+            USED -= block.length;
             Algorithm::close(block);
         }
     }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        unsafe {
+            let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+            let order = layout_order(layout);
+            let new_order = layout_order(new_layout);
+            if new_order == order {
+                return ptr;
+            }
+            if Algorithm::resize(ptr.offset_from(PTR_ALLOC) as usize, order, new_order) {
+                USED = USED - (MIN_BLOCK << order) + (MIN_BLOCK << new_order);
+                PEAK = PEAK.max(USED);
+                return ptr;
+            }
+            let new_ptr = self.alloc(new_layout);
+            if !new_ptr.is_null() {
+                core::ptr::copy_nonoverlapping(ptr, new_ptr, min(layout.size(), new_size));
+                self.dealloc(ptr, layout);
+            }
+            new_ptr
+        }
+    }
 }
 
 unsafe impl Sync for Allocator {}
 
+// Parses a decimal build time setting, falling back to the default when it is not set.
+const fn parse_env(s: Option<&str>, default: usize) -> usize {
+    let s = match s {
+        Some(s) => s.as_bytes(),
+        None => return default,
+    };
+    assert!(!s.is_empty(), "heap settings must be decimal numbers");
+    let mut n: usize = 0;
+    let mut i = 0;
+    while i < s.len() {
+        assert!(s[i].is_ascii_digit(), "heap settings must be decimal numbers");
+        n = n * 10 + (s[i] - b'0') as usize;
+        i += 1;
+    }
+    n
+}
+
+// The order of the smallest block that holds the layout at its alignment. A block is aligned to its size, so the size
+// is rounded up to the alignment.
+fn layout_order(layout: Layout) -> usize {
+    log2(MIN_BLOCK, clp2(layout.size()).max(layout.align()).max(MIN_BLOCK))
+}
+
 fn clp2(n: usize) -> usize {
     n.next_power_of_two()
 }
@@ -162,12 +297,183 @@ fn log2(m: usize, n: usize) -> usize {
     n.next_power_of_two().trailing_zeros() as usize - m.trailing_zeros() as usize
 }
 
-fn uldr(p: *mut u8) -> usize {
-    unsafe { *(p as *const usize) }
+// The first bit of an order in the free bitmap. Order k has 1 << (MAX_ORDER - k) blocks.
+const fn bitmap_base(order: usize) -> usize {
+    (2 << MAX_ORDER) - (2 << (MAX_ORDER - order))
 }
 
-fn ustr(p: *mut u8, n: usize) {
+// Loads and stores the i-th word of a block, used for the links of free blocks: word 0 is the previous block and word
+// 1 is the next block.
+fn uldr(p: *mut u8, i: usize) -> usize {
+    unsafe { *(p as *const usize).add(i) }
+}
+
+fn ustr(p: *mut u8, i: usize, n: usize) {
     unsafe {
-        *(p as *mut usize) = n;
+        *(p as *mut usize).add(i) = n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    // The pool is global, so tests that use it must not run concurrently.
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    fn alloc(layout: Layout) -> *mut u8 {
+        unsafe { Allocator {}.alloc(layout) }
+    }
+
+    fn dealloc(ptr: *mut u8, layout: Layout) {
+        unsafe { Allocator {}.dealloc(ptr, layout) }
+    }
+
+    #[test]
+    fn test_pool_alignment() {
+        assert_eq!(PTR_ALLOC as usize % POOL_ALIGN, 0);
+    }
+
+    #[test]
+    fn test_alignment() {
+        let _guard = LOCK.lock().unwrap();
+        let mut align = 1;
+        while align <= POOL_ALIGN {
+            for size in [1, 7, 16, 100, align, align + 1, 3 * align] {
+                let layout = Layout::from_size_align(size, align).unwrap();
+                // Allocate twice so the second block does not start at the beginning of the pool.
+                let a = alloc(layout);
+                let b = alloc(layout);
+                assert!(!a.is_null() && !b.is_null());
+                assert_eq!(a as usize % align, 0);
+                assert_eq!(b as usize % align, 0);
+                dealloc(b, layout);
+                dealloc(a, layout);
+            }
+            align <<= 1;
+        }
+        assert_eq!(Algorithm::avail(), MAX_TOTAL);
+    }
+
+    #[test]
+    fn test_alignment_larger_than_size() {
+        let _guard = LOCK.lock().unwrap();
+        let small = Layout::from_size_align(8, 8).unwrap();
+        let large = Layout::from_size_align(16, 1024).unwrap();
+        let mut ptrs = Vec::new();
+        for _ in 0..4 {
+            ptrs.push((alloc(small), small));
+            ptrs.push((alloc(large), large));
+        }
+        for (i, &(ptr, layout)) in ptrs.iter().enumerate() {
+            assert_eq!(ptr as usize % layout.align(), 0);
+            // A block must not overlap any other block.
+            for (j, &(other, other_layout)) in ptrs.iter().enumerate() {
+                if i != j {
+                    assert!(
+                        ptr as usize + layout.align().max(layout.size()) <= other as usize
+                            || other as usize + other_layout.align().max(other_layout.size()) <= ptr as usize
+                    );
+                }
+            }
+        }
+        for (ptr, layout) in ptrs {
+            dealloc(ptr, layout);
+        }
+        assert_eq!(Algorithm::avail(), MAX_TOTAL);
+    }
+
+    #[test]
+    fn test_realloc_same_order() {
+        let _guard = LOCK.lock().unwrap();
+        let layout = Layout::from_size_align(100, 8).unwrap();
+        let a = alloc(layout);
+        let b = unsafe { Allocator {}.realloc(a, layout, 128) };
+        assert_eq!(a, b);
+        let b = unsafe { Allocator {}.realloc(b, Layout::from_size_align(128, 8).unwrap(), 65) };
+        assert_eq!(a, b);
+        dealloc(b, Layout::from_size_align(65, 8).unwrap());
+        assert_eq!(Algorithm::avail(), MAX_TOTAL);
+    }
+
+    #[test]
+    fn test_realloc_in_place() {
+        let _guard = LOCK.lock().unwrap();
+        let layout = Layout::from_size_align(MIN_BLOCK, 8).unwrap();
+        let a = alloc(layout);
+        unsafe { a.write_bytes(0x2a, MIN_BLOCK) };
+        // The pool is empty, so the block grows into its free buddies.
+        let grown = Layout::from_size_align(MIN_BLOCK * 16, 8).unwrap();
+        let b = unsafe { Allocator {}.realloc(a, layout, grown.size()) };
+        assert_eq!(a, b);
+        assert!((0..MIN_BLOCK).all(|i| unsafe { *b.add(i) } == 0x2a));
+        // Shrinking frees the upper part, which is then available to others.
+        let c = unsafe { Allocator {}.realloc(b, grown, MIN_BLOCK) };
+        assert_eq!(a, c);
+        let d = alloc(layout);
+        assert_eq!(d as usize, a as usize + MIN_BLOCK);
+        dealloc(d, layout);
+        dealloc(c, layout);
+        assert_eq!(Algorithm::avail(), MAX_TOTAL);
+    }
+
+    #[test]
+    fn test_realloc_move() {
+        let _guard = LOCK.lock().unwrap();
+        let layout = Layout::from_size_align(MIN_BLOCK, 8).unwrap();
+        let a = alloc(layout);
+        let b = alloc(layout);
+        unsafe { a.write_bytes(0x2a, MIN_BLOCK) };
+        // The buddy of the first block is taken, so it has to move.
+        let grown = Layout::from_size_align(MIN_BLOCK * 4, 8).unwrap();
+        let c = unsafe { Allocator {}.realloc(a, layout, grown.size()) };
+        assert_ne!(a, c);
+        assert!((0..MIN_BLOCK).all(|i| unsafe { *c.add(i) } == 0x2a));
+        dealloc(b, layout);
+        dealloc(c, grown);
+        assert_eq!(Algorithm::avail(), MAX_TOTAL);
+    }
+
+    #[test]
+    fn test_random() {
+        let _guard = LOCK.lock().unwrap();
+        let mut seed: u64 = 42;
+        let mut rand = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+        let mut live: Vec<(*mut u8, Layout)> = Vec::new();
+        for _ in 0..4096 {
+            if live.is_empty() || (live.len() < 256 && rand() % 3 != 0) {
+                let layout = Layout::from_size_align(1 + rand() % 2048, 8).unwrap();
+                let ptr = alloc(layout);
+                assert!(!ptr.is_null());
+                unsafe { ptr.write_bytes(live.len() as u8, layout.size()) };
+                live.push((ptr, layout));
+            } else {
+                let (ptr, layout) = live.swap_remove(rand() % live.len());
+                dealloc(ptr, layout);
+            }
+        }
+        // Freed blocks were never handed out twice, so every live block still holds its own pattern.
+        for (ptr, layout) in live.iter_mut() {
+            let b = unsafe { *ptr.add(0) };
+            assert!((0..layout.size()).all(|i| unsafe { *ptr.add(i) } == b));
+            unsafe { ptr.write_bytes(0, layout.size()) };
+        }
+        for (ptr, layout) in live {
+            dealloc(ptr, layout);
+        }
+        assert_eq!(Algorithm::avail(), MAX_TOTAL);
+        assert!(Algorithm::is_free(0, MAX_ORDER));
+    }
+
+    #[test]
+    fn test_alignment_larger_than_pool() {
+        let _guard = LOCK.lock().unwrap();
+        let layout = Layout::from_size_align(8, POOL_ALIGN * 2).unwrap();
+        assert!(alloc(layout).is_null());
     }
 }
@@ -0,0 +1,241 @@
+//! Message framing and a request/response protocol between spawned scripts.
+//!
+//! A frame is a molecule Bytes: the payload length as a 4-byte little endian integer followed by the payload. On top
+//! of frames, a request is the molecule table (method: Uint32, payload: Bytes) and a response is the molecule table
+//! (code: Uint32, payload: Bytes), where code zero means success and any other value is an error reported by the
+//! server.
+//!
+//! Frames longer than the maximum frame size of the channel are rejected before anything is allocated for them.
+
+use crate::args::Arg;
+use crate::io::{BufReader, Read, Write};
+use crate::process::Fd;
+use crate::syscall::SysError;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The default maximum frame size of a channel.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// The code sent for a handler error of zero, which the client would take for success.
+pub const ZERO_ERROR: u32 = u32::MAX;
+
+/// A bidirectional, message oriented channel over a reader and a writer.
+pub struct Channel<R: Read, W: Write> {
+    reader: BufReader<R>,
+    writer: W,
+    max_frame_size: usize,
+}
+
+impl<R: Read, W: Write> Channel<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader: BufReader::new(reader), writer, max_frame_size: MAX_FRAME_SIZE }
+    }
+
+    /// Sets the largest message recv accepts.
+    pub fn set_max_frame_size(&mut self, size: usize) {
+        self.max_frame_size = size;
+    }
+
+    /// Sends one message.
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), SysError> {
+        self.writer.write_all(&crate::molecule::encode_bytes(payload))?;
+        self.writer.flush()
+    }
+
+    /// Receives one message. Other end closed is reported if the peer closed the channel, and slice out of bound if
+    /// the message is longer than the maximum frame size.
+    pub fn recv(&mut self) -> Result<Vec<u8>, SysError> {
+        let mut head = [0; 4];
+        self.reader.read_exact(&mut head)?;
+        let size = u32::from_le_bytes(head) as usize;
+        if size > self.max_frame_size {
+            return Err(SysError::SliceOutOfBound);
+        }
+        let mut body = vec![0; size];
+        self.reader.read_exact(&mut body)?;
+        Ok(body)
+    }
+
+    pub fn into_inner(self) -> (BufReader<R>, W) {
+        (self.reader, self.writer)
+    }
+}
+
+/// Errors of a remote call.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CallError {
+    /// The channel failed.
+    Sys(SysError),
+    /// The server handled the request and returned a non zero code.
+    Remote(u32),
+}
+
+impl From<SysError> for CallError {
+    fn from(e: SysError) -> Self {
+        CallError::Sys(e)
+    }
+}
+
+/// The calling side of the protocol, usually the parent script.
+pub struct Client<R: Read = Fd, W: Write = Fd> {
+    channel: Channel<R, W>,
+}
+
+impl<R: Read, W: Write> Client<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { channel: Channel::new(reader, writer) }
+    }
+
+    /// Sends a request and waits for its response.
+    pub fn call(&mut self, method: u32, payload: &[u8]) -> Result<Vec<u8>, CallError> {
+        let request = crate::molecule::encode_dynvec(vec![
+            crate::molecule::encode_u32(method),
+            crate::molecule::encode_bytes(payload),
+        ]);
+        self.channel.send(&request)?;
+        let response = self.channel.recv()?;
+        match decode_message(&response)? {
+            (0, payload) => Ok(payload),
+            (code, _) => Err(CallError::Remote(code)),
+        }
+    }
+
+    pub fn into_inner(self) -> Channel<R, W> {
+        self.channel
+    }
+}
+
+/// The serving side of the protocol, usually a long lived child script.
+pub struct Server<R: Read = Fd, W: Write = Fd> {
+    channel: Channel<R, W>,
+}
+
+impl<R: Read, W: Write> Server<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { channel: Channel::new(reader, writer) }
+    }
+
+    /// Answers requests with the handler until the client closes the channel. The handler receives the method and
+    /// the payload, and returns the response payload or an error code. An error code of zero is sent as ZERO_ERROR,
+    /// so a failed request never reads as success.
+    pub fn serve<F>(&mut self, mut handler: F) -> Result<(), SysError>
+    where
+        F: FnMut(u32, &[u8]) -> Result<Vec<u8>, u32>,
+    {
+        loop {
+            let request = match self.channel.recv() {
+                Ok(request) => request,
+                Err(SysError::OtherEndClosed) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            let (method, payload) = decode_message(&request)?;
+            let (code, payload) = match handler(method, &payload) {
+                Ok(payload) => (0, payload),
+                Err(0) => (ZERO_ERROR, Vec::new()),
+                Err(code) => (code, Vec::new()),
+            };
+            let response = crate::molecule::encode_dynvec(vec![
+                crate::molecule::encode_u32(code),
+                crate::molecule::encode_bytes(&payload),
+            ]);
+            self.channel.send(&response)?;
+        }
+    }
+
+    pub fn into_inner(self) -> Channel<R, W> {
+        self.channel
+    }
+}
+
+// Decodes a request or a response, reporting wrong format for malformed messages.
+fn decode_message(data: &[u8]) -> Result<(u32, Vec<u8>), SysError> {
+    let head = crate::args::check_table(data, 2)?;
+    if head[1] - head[0] != 4 {
+        return Err(SysError::WrongFormat);
+    }
+    let code = crate::molecule::decode_u32(&data[head[0]..head[1]]);
+    let payload = Vec::<u8>::molecule_decode(&data[head[1]..])?;
+    Ok((code, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // In-memory ends of a pipe.
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, SysError> {
+            let n = buf.len().min(self.len());
+            buf[..n].copy_from_slice(&self[..n]);
+            *self = &self[n..];
+            Ok(n)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, SysError> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn message(code: u32, payload: &[u8]) -> Vec<u8> {
+        crate::molecule::encode_bytes(&crate::molecule::encode_dynvec(vec![
+            crate::molecule::encode_u32(code),
+            crate::molecule::encode_bytes(payload),
+        ]))
+    }
+
+    #[test]
+    fn test_call() {
+        let input = message(0, b"pong");
+        let mut client = Client::new(&input[..], Vec::new());
+        assert_eq!(client.call(1, b"ping"), Ok(b"pong".to_vec()));
+        assert_eq!(client.into_inner().into_inner().1, message(1, b"ping"));
+        let input = message(7, b"");
+        let mut client = Client::new(&input[..], Vec::new());
+        assert_eq!(client.call(1, b"ping"), Err(CallError::Remote(7)));
+    }
+
+    #[test]
+    fn test_serve() {
+        let mut input = message(1, b"x");
+        input.extend(message(2, b"x"));
+        input.extend(message(3, b"x"));
+        let mut server = Server::new(&input[..], Vec::new());
+        server
+            .serve(|method, payload| match method {
+                1 => Ok(payload.to_vec()),
+                2 => Err(9),
+                _ => Err(0),
+            })
+            .unwrap();
+        let mut expect = message(0, b"x");
+        expect.extend(message(9, b""));
+        expect.extend(message(ZERO_ERROR, b""));
+        assert_eq!(server.into_inner().into_inner().1, expect);
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let input = crate::molecule::encode_bytes(&[0; 100]);
+        let mut channel = Channel::new(&input[..], Vec::new());
+        channel.set_max_frame_size(99);
+        assert_eq!(channel.recv(), Err(SysError::SliceOutOfBound));
+        let input = [0xff, 0xff, 0xff, 0xff];
+        let mut channel = Channel::new(&input[..], Vec::new());
+        assert_eq!(channel.recv(), Err(SysError::SliceOutOfBound));
+    }
+
+    #[test]
+    fn test_malformed_message() {
+        for frame in [vec![], vec![1, 2, 3], crate::molecule::encode_dynvec(vec![vec![1, 2], vec![0; 4]])] {
+            let input = crate::molecule::encode_bytes(&frame);
+            let mut client = Client::new(&input[..], Vec::new());
+            assert_eq!(client.call(1, b""), Err(CallError::Sys(SysError::WrongFormat)));
+            let mut server = Server::new(&input[..], Vec::new());
+            assert_eq!(server.serve(|_, _| Ok(Vec::new())), Err(SysError::WrongFormat));
+        }
+    }
+}
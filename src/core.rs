@@ -12,6 +12,130 @@ pub const SOURCE_CELL_DEP: u64 = 3;
 pub const SOURCE_HEADER_DEP: u64 = 4;
 pub const SOURCE_GROUP_INPUT: u64 = 0x0100000000000001;
 pub const SOURCE_GROUP_OUTPUT: u64 = 0x0100000000000002;
+pub const SOURCE_GROUP_CELL_DEP: u64 = 0x0100000000000003;
+pub const SOURCE_GROUP_HEADER_DEP: u64 = 0x0100000000000004;
+
+/// The part of the transaction a cell, its data, an input or a witness is loaded from. Headers are loaded from a
+/// HeaderSource instead, so passing a header dep to a cell loader does not compile.
+///
+/// Group sources only contain the items that run the current script, in transaction order. Since ckb2023 the vm also
+/// accepts a group variant of cell deps, but a script group is formed by inputs and outputs only, so this source is
+/// always empty and every load from it reports index out of bound.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Source {
+    Input,
+    Output,
+    CellDep,
+    GroupInput,
+    GroupOutput,
+    GroupCellDep,
+    /// A source value passed to the vm as is. Useful for sources added to the vm after this crate.
+    Raw(u64),
+}
+
+impl Source {
+    pub fn raw(self) -> u64 {
+        match self {
+            Source::Input => SOURCE_INPUT,
+            Source::Output => SOURCE_OUTPUT,
+            Source::CellDep => SOURCE_CELL_DEP,
+            Source::GroupInput => SOURCE_GROUP_INPUT,
+            Source::GroupOutput => SOURCE_GROUP_OUTPUT,
+            Source::GroupCellDep => SOURCE_GROUP_CELL_DEP,
+            Source::Raw(source) => source,
+        }
+    }
+}
+
+impl From<Source> for u64 {
+    fn from(source: Source) -> u64 {
+        source.raw()
+    }
+}
+
+/// The part of the transaction a header or a block extension is loaded from. Input and CellDep select the block the
+/// cell was created in, which must be listed in the header deps, and HeaderDep selects a header dep directly. Outputs
+/// are not in any block yet, so there is no variant for them.
+///
+/// Like GroupCellDep, the group variants of cell deps and header deps are always empty.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HeaderSource {
+    Input,
+    CellDep,
+    HeaderDep,
+    GroupInput,
+    GroupCellDep,
+    GroupHeaderDep,
+    /// A source value passed to the vm as is. Useful for sources added to the vm after this crate.
+    Raw(u64),
+}
+
+impl HeaderSource {
+    pub fn raw(self) -> u64 {
+        match self {
+            HeaderSource::Input => SOURCE_INPUT,
+            HeaderSource::CellDep => SOURCE_CELL_DEP,
+            HeaderSource::HeaderDep => SOURCE_HEADER_DEP,
+            HeaderSource::GroupInput => SOURCE_GROUP_INPUT,
+            HeaderSource::GroupCellDep => SOURCE_GROUP_CELL_DEP,
+            HeaderSource::GroupHeaderDep => SOURCE_GROUP_HEADER_DEP,
+            HeaderSource::Raw(source) => source,
+        }
+    }
+}
+
+impl From<HeaderSource> for u64 {
+    fn from(source: HeaderSource) -> u64 {
+        source.raw()
+    }
+}
+
+pub const PLACE_CELL_DATA: u64 = 0;
+pub const PLACE_WITNESS: u64 = 1;
+
+/// Where exec and spawn read the program from. The index and source select the cell whose data is used, or the
+/// witness at the same index and source.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Place {
+    CellData,
+    Witness,
+}
+
+impl Place {
+    pub fn raw(self) -> u64 {
+        match self {
+            Place::CellData => PLACE_CELL_DATA,
+            Place::Witness => PLACE_WITNESS,
+        }
+    }
+}
+
+/// Fields of a cell that can be loaded individually with load_cell_by_field.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CellField {
+    Capacity = 0,
+    DataHash = 1,
+    Lock = 2,
+    LockHash = 3,
+    Type = 4,
+    TypeHash = 5,
+    OccupiedCapacity = 6,
+}
+
+/// Fields of a header that can be loaded individually with load_header_by_field.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HeaderField {
+    EpochNumber = 0,
+    EpochStartBlockNumber = 1,
+    EpochLength = 2,
+}
+
+/// Fields of a cell input that can be loaded individually with load_input_by_field.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InputField {
+    OutPoint = 0,
+    Since = 1,
+}
 
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct Script {
@@ -264,11 +388,12 @@ impl Transaction {
         }
     }
 
+    #[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
     pub fn hash_sighash_all(&self) -> [u8; 32] {
         let mut group: Vec<usize> = Vec::new();
         let script_hash = crate::syscall::load_script_hash();
         for i in 0..self.raw.inputs.len() {
-            let search_hash = crate::syscall::load_cell_by_field(i as u64, SOURCE_INPUT, 3);
+            let search_hash = crate::syscall::load_cell_lock_hash(i as u64, Source::Input);
             if search_hash == script_hash {
                 group.push(i);
             }
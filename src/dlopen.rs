@@ -0,0 +1,358 @@
+//! A minimal dynamic loader for shared libraries stored in cells.
+//!
+//! The loader follows the same rules as ckb_dlfcn.h from ckb-c-stdlib, so libraries built for ckb-dlopen can be used
+//! from ckbes scripts. The library must be a position independent RISC-V ELF whose only relocations are
+//! R_RISCV_RELATIVE. Executable segments are mapped with load_cell_data_as_code, other segments are copied, then the
+//! relocations are applied and exported symbols are resolved through the dynamic symbol table.
+
+use crate::core::Source;
+use crate::syscall::SysError;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+pub const PAGE_SIZE: usize = 4096;
+
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const SHT_RELA: u32 = 4;
+const SHT_DYNSYM: u32 = 11;
+const R_RISCV_RELATIVE: u64 = 3;
+
+/// Page aligned memory that a library can be loaded into. It is usually placed in a static, since N is typically far
+/// larger than the stack.
+#[repr(C, align(4096))]
+pub struct CodeMemory<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> CodeMemory<N> {
+    pub const fn new() -> Self {
+        assert!(N.is_multiple_of(PAGE_SIZE));
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> Default for CodeMemory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A library loaded into memory borrowed from the caller.
+pub struct Library<'a> {
+    base: *const u8,
+    consumed: usize,
+    dynsym: usize,
+    dynsym_size: usize,
+    dynstr: usize,
+    memory: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> Library<'a> {
+    /// Loads the library in the given cell into mem. The memory must be page aligned and its length a multiple of the
+    /// page size. Wrong format is reported for files the loader does not understand, and slice out of bound when the
+    /// memory is too small.
+    pub fn open(index: u64, source: Source, mem: &'a mut [u8]) -> Result<Self, SysError> {
+        assert!((mem.as_ptr() as usize).is_multiple_of(PAGE_SIZE));
+        assert!(mem.len().is_multiple_of(PAGE_SIZE));
+        let mut ehdr = [0; 64];
+        load(index, source, 0, &mut ehdr)?;
+        if ehdr[0..4] != *b"\x7fELF" || ehdr[4] != 2 || ehdr[5] != 1 || u16le(&ehdr, 18) != 0xf3 {
+            return Err(SysError::WrongFormat);
+        }
+        let phoff = u64le(&ehdr, 32);
+        let shoff = u64le(&ehdr, 40);
+        let phentsize = u16le(&ehdr, 54) as usize;
+        let phnum = u16le(&ehdr, 56) as usize;
+        let shentsize = u16le(&ehdr, 58) as usize;
+        let shnum = u16le(&ehdr, 60) as usize;
+        if phentsize != 56 || shentsize != 64 || phnum > 16 || shnum > 32 {
+            return Err(SysError::WrongFormat);
+        }
+
+        let mut consumed = 0;
+        // Pages mapped as code are no longer writable, so nothing may be written there after they are loaded.
+        let mut code: Vec<(usize, usize)> = Vec::new();
+        let mut phdrs = vec![0; phnum * phentsize];
+        load(index, source, phoff, &mut phdrs)?;
+        for phdr in phdrs.chunks(phentsize) {
+            let p_type = u32le(phdr, 0);
+            let p_flags = u32le(phdr, 4);
+            let p_offset = u64le(phdr, 8) as usize;
+            let p_vaddr = u64le(phdr, 16) as usize;
+            let p_filesz = u64le(phdr, 32) as usize;
+            let p_memsz = u64le(phdr, 40) as usize;
+            if p_type != PT_LOAD || p_memsz == 0 {
+                continue;
+            }
+            if p_filesz > p_memsz {
+                return Err(SysError::WrongFormat);
+            }
+            if p_flags & PF_X != 0 {
+                let prepad = p_vaddr % PAGE_SIZE;
+                let start = p_vaddr - prepad;
+                let end = add(p_vaddr, p_memsz)?.checked_next_multiple_of(PAGE_SIZE).ok_or(SysError::WrongFormat)?;
+                if prepad > p_offset {
+                    return Err(SysError::WrongFormat);
+                }
+                if end > mem.len() {
+                    return Err(SysError::SliceOutOfBound);
+                }
+                crate::syscall::try_load_cell_data_as_code(
+                    index,
+                    source,
+                    (p_offset - prepad) as u64,
+                    add(p_filesz, prepad)? as u64,
+                    &mut mem[start..end],
+                )?;
+                code.push((start, end));
+                consumed = consumed.max(end);
+            } else {
+                let end = add(p_vaddr, p_memsz)?;
+                if end > mem.len() {
+                    return Err(SysError::SliceOutOfBound);
+                }
+                if overlaps(&code, p_vaddr, end) {
+                    return Err(SysError::WrongFormat);
+                }
+                load(index, source, p_offset as u64, &mut mem[p_vaddr..p_vaddr + p_filesz])?;
+                mem[p_vaddr + p_filesz..end].fill(0);
+                consumed = consumed.max(end.next_multiple_of(PAGE_SIZE));
+            }
+        }
+
+        let mut dynsym = None;
+        let mut shdrs = vec![0; shnum * shentsize];
+        load(index, source, shoff, &mut shdrs)?;
+        for shdr in shdrs.chunks(shentsize) {
+            let sh_type = u32le(shdr, 4);
+            let sh_offset = u64le(shdr, 24);
+            let sh_size = u64le(shdr, 32) as usize;
+            let sh_entsize = u64le(shdr, 56) as usize;
+            if sh_type == SHT_RELA {
+                if sh_entsize != 24 {
+                    return Err(SysError::WrongFormat);
+                }
+                // Relocations are read in batches, so a bogus section size cannot exhaust the heap.
+                let mut relas = [0; 24 * 64];
+                let mut done = 0;
+                while done < sh_size - sh_size % 24 {
+                    let size = relas.len().min(sh_size - sh_size % 24 - done);
+                    let offset = sh_offset.checked_add(done as u64).ok_or(SysError::WrongFormat)?;
+                    load(index, source, offset, &mut relas[..size])?;
+                    for rela in relas[..size].chunks(24) {
+                        let r_offset = u64le(rela, 0) as usize;
+                        let r_info = u64le(rela, 8);
+                        let r_addend = u64le(rela, 16) as usize;
+                        if r_info != R_RISCV_RELATIVE {
+                            return Err(SysError::WrongFormat);
+                        }
+                        let r_end = add(r_offset, 8)?;
+                        if r_end > consumed || r_addend > consumed || overlaps(&code, r_offset, r_end) {
+                            return Err(SysError::WrongFormat);
+                        }
+                        let value = mem.as_ptr() as u64 + r_addend as u64;
+                        mem[r_offset..r_end].copy_from_slice(&value.to_le_bytes());
+                    }
+                    done += size;
+                }
+            }
+            if sh_type == SHT_DYNSYM {
+                if sh_entsize != 24 {
+                    return Err(SysError::WrongFormat);
+                }
+                dynsym = Some(shdr);
+            }
+        }
+
+        // The dynamic symbol table and its string table are part of a loaded segment, so the symbols are resolved
+        // from memory. The string table is the section linked by the symbol table.
+        let (dynsym, dynsym_size, dynstr) = match dynsym {
+            Some(shdr) => {
+                let link = u32le(shdr, 40) as usize;
+                if link >= shnum {
+                    return Err(SysError::WrongFormat);
+                }
+                let dynsym = u64le(shdr, 16) as usize;
+                let dynsym_size = u64le(shdr, 32) as usize / 24;
+                let dynstr = u64le(&shdrs[link * shentsize..], 16) as usize;
+                let dynsym_end = dynsym_size.checked_mul(24).and_then(|e| e.checked_add(dynsym));
+                if dynsym_end.is_none_or(|e| e > consumed) || dynstr >= consumed {
+                    return Err(SysError::WrongFormat);
+                }
+                (dynsym, dynsym_size, dynstr)
+            }
+            None => (0, 0, 0),
+        };
+        Ok(Self { base: mem.as_ptr(), consumed, dynsym, dynsym_size, dynstr, memory: PhantomData })
+    }
+
+    /// Number of bytes of the memory used by the library, rounded up to the page size. The rest of the memory can be
+    /// used to load another library.
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// Finds the address of an exported symbol.
+    pub fn get(&self, name: &str) -> Option<*const u8> {
+        let name = name.as_bytes();
+        for i in 0..self.dynsym_size {
+            let sym = unsafe { core::slice::from_raw_parts(self.base.add(self.dynsym + i * 24), 24) };
+            let st_name = u32le(sym, 0) as usize;
+            let st_shndx = u16le(sym, 6);
+            let st_value = u64le(sym, 8) as usize;
+            let name_end = st_name.checked_add(self.dynstr).and_then(|e| e.checked_add(name.len()));
+            if st_shndx == 0 || name_end.is_none_or(|e| e >= self.consumed) || st_value >= self.consumed {
+                continue;
+            }
+            let candidate =
+                unsafe { core::slice::from_raw_parts(self.base.add(self.dynstr + st_name), name.len() + 1) };
+            if candidate[..name.len()] == *name && candidate[name.len()] == 0 {
+                return Some(unsafe { self.base.add(st_value) });
+            }
+        }
+        None
+    }
+
+    /// Finds an exported symbol and casts its address to T, which is usually an extern "C" function pointer.
+    ///
+    /// # Safety
+    ///
+    /// T must be pointer sized and match the actual type of the symbol.
+    pub unsafe fn symbol<T: Copy>(&self, name: &str) -> Option<T> {
+        assert!(core::mem::size_of::<T>() == core::mem::size_of::<*const u8>());
+        self.get(name).map(|addr| unsafe { core::mem::transmute_copy(&addr) })
+    }
+}
+
+fn add(a: usize, b: usize) -> Result<usize, SysError> {
+    a.checked_add(b).ok_or(SysError::WrongFormat)
+}
+
+// Returns whether [start, end) intersects any of the ranges.
+fn overlaps(ranges: &[(usize, usize)], start: usize, end: usize) -> bool {
+    ranges.iter().any(|&(a, b)| start < b && a < end)
+}
+
+fn load(index: u64, source: Source, offset: u64, buf: &mut [u8]) -> Result<(), SysError> {
+    let len = crate::syscall::try_load_cell_data_range(index, source, offset, buf)?;
+    if len < buf.len() as u64 {
+        return Err(SysError::WrongFormat);
+    }
+    Ok(())
+}
+
+fn u16le(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64le(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CellOutput, Script};
+
+    // A library whose first page is code and whose second page holds the symbol table, the string table and one
+    // relocation. The relocation section and the section headers are only in the file.
+    fn image() -> Vec<u8> {
+        let mut data = vec![0; 0x1600];
+        let mut put = |offset: usize, value: &[u8]| data[offset..offset + value.len()].copy_from_slice(value);
+        put(0, b"\x7fELF\x02\x01");
+        put(18, &0xf3u16.to_le_bytes());
+        put(32, &64u64.to_le_bytes());
+        put(40, &0x1400u64.to_le_bytes());
+        put(54, &56u16.to_le_bytes());
+        put(56, &2u16.to_le_bytes());
+        put(58, &64u16.to_le_bytes());
+        put(60, &4u16.to_le_bytes());
+        // Program headers: type, flags, offset, vaddr, filesz and memsz.
+        for (i, (flags, offset, filesz, memsz)) in
+            [(5u32, 0u64, 0x1000u64, 0x1000u64), (6, 0x1000, 0x180, 0x200)].into_iter().enumerate()
+        {
+            let phdr = 64 + i * 56;
+            put(phdr, &PT_LOAD.to_le_bytes());
+            put(phdr + 4, &flags.to_le_bytes());
+            put(phdr + 8, &offset.to_le_bytes());
+            put(phdr + 16, &offset.to_le_bytes());
+            put(phdr + 32, &filesz.to_le_bytes());
+            put(phdr + 40, &memsz.to_le_bytes());
+        }
+        // Section headers: type, addr, offset, size, link and entsize. The first one is null.
+        for (i, (sh_type, addr, size, link, entsize)) in
+            [(SHT_DYNSYM, 0x1000u64, 48u64, 2u32, 24u64), (3, 0x1100, 5, 0, 0), (SHT_RELA, 0x1200, 24, 0, 24)]
+                .into_iter()
+                .enumerate()
+        {
+            let shdr = 0x1400 + (i + 1) * 64;
+            put(shdr + 4, &sh_type.to_le_bytes());
+            put(shdr + 16, &addr.to_le_bytes());
+            put(shdr + 24, &addr.to_le_bytes());
+            put(shdr + 32, &size.to_le_bytes());
+            put(shdr + 40, &link.to_le_bytes());
+            put(shdr + 56, &entsize.to_le_bytes());
+        }
+        // Symbol 1 is foo, defined in section 1 at 0x10.
+        put(0x1018, &1u32.to_le_bytes());
+        put(0x1018 + 6, &1u16.to_le_bytes());
+        put(0x1018 + 8, &0x10u64.to_le_bytes());
+        put(0x1100, b"\0foo\0");
+        // A relative relocation of 0x1180 to 0x10.
+        put(0x1200, &0x1180u64.to_le_bytes());
+        put(0x1208, &R_RISCV_RELATIVE.to_le_bytes());
+        put(0x1210, &0x10u64.to_le_bytes());
+        data
+    }
+
+    fn open<const N: usize>(data: Vec<u8>, mem: &mut CodeMemory<N>) -> Result<Library<'_>, SysError> {
+        let cell = CellOutput::new(0, Script::default(), None);
+        crate::mock::Context::new(Script::default()).cell_dep(cell, data).install();
+        Library::open(0, Source::CellDep, &mut mem.0)
+    }
+
+    #[test]
+    fn test_open() {
+        let mut mem = CodeMemory::<{ 4 * PAGE_SIZE }>::new();
+        let library = open(image(), &mut mem).unwrap();
+        assert_eq!(library.consumed(), 2 * PAGE_SIZE);
+        let base = library.base;
+        assert_eq!(library.get("foo"), Some(base.wrapping_add(0x10)));
+        assert_eq!(library.get("fo"), None);
+        assert_eq!(library.get("bar"), None);
+        assert_eq!(u64le(&mem.0, 0x1180), base as u64 + 0x10);
+        assert_eq!(mem.0[0..4], *b"\x7fELF");
+    }
+
+    #[test]
+    fn test_bad_magic() {
+        let mut data = image();
+        data[0] = 0;
+        assert_eq!(open(data, &mut CodeMemory::<{ 4 * PAGE_SIZE }>::new()).err(), Some(SysError::WrongFormat));
+    }
+
+    #[test]
+    fn test_memory_too_small() {
+        assert_eq!(open(image(), &mut CodeMemory::<PAGE_SIZE>::new()).err(), Some(SysError::SliceOutOfBound));
+    }
+
+    #[test]
+    fn test_relocation_into_code() {
+        let mut data = image();
+        data[0x1200..0x1208].copy_from_slice(&0x10u64.to_le_bytes());
+        assert_eq!(open(data, &mut CodeMemory::<{ 4 * PAGE_SIZE }>::new()).err(), Some(SysError::WrongFormat));
+    }
+
+    #[test]
+    fn test_overflowing_vaddr() {
+        let mut data = image();
+        data[64 + 16..64 + 24].copy_from_slice(&(u64::MAX - 4095).to_le_bytes());
+        data[64 + 32..64 + 40].copy_from_slice(&1u64.to_le_bytes());
+        data[64 + 40..64 + 48].copy_from_slice(&1u64.to_le_bytes());
+        assert_eq!(open(data, &mut CodeMemory::<{ 4 * PAGE_SIZE }>::new()).err(), Some(SysError::WrongFormat));
+    }
+}
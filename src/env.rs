@@ -0,0 +1,98 @@
+//! Access to the arguments of the script, in the spirit of std::env.
+//!
+//! Arguments are passed by exec and spawn as nul terminated byte strings, which need not be valid utf-8. The views
+//! returned here borrow the original argv, so they cost no allocation and preserve every byte.
+//!
+//! With the mock feature the arguments are those of the installed mock::Context, and are kept per thread.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{CStr, c_char};
+#[cfg(not(feature = "mock"))]
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+#[cfg(not(feature = "mock"))]
+static ARGC: AtomicUsize = AtomicUsize::new(0);
+#[cfg(not(feature = "mock"))]
+static ARGV: AtomicPtr<*const c_char> = AtomicPtr::new(core::ptr::null_mut());
+
+#[cfg(feature = "mock")]
+std::thread_local! {
+    static ARGS: core::cell::Cell<(usize, *const *const c_char)> = const { core::cell::Cell::new((0, core::ptr::null())) };
+}
+
+/// Records argv. Called once by _start before main; scripts that define their own _start must call it themselves.
+///
+/// # Safety
+///
+/// argv must point to argc valid nul terminated strings that live until the script exits.
+pub unsafe fn init(argc: usize, argv: *const *const c_char) {
+    #[cfg(not(feature = "mock"))]
+    {
+        ARGV.store(argv as *mut _, Ordering::Relaxed);
+        ARGC.store(argc, Ordering::Relaxed);
+    }
+    #[cfg(feature = "mock")]
+    ARGS.with(|e| e.set((argc, argv)));
+}
+
+#[cfg(not(feature = "mock"))]
+fn argv() -> (usize, *const *const c_char) {
+    (ARGC.load(Ordering::Relaxed), ARGV.load(Ordering::Relaxed))
+}
+
+#[cfg(feature = "mock")]
+fn argv() -> (usize, *const *const c_char) {
+    ARGS.with(|e| e.get())
+}
+
+/// An iterator over the arguments of the script.
+#[derive(Clone, Debug)]
+pub struct Args {
+    range: core::ops::Range<usize>,
+}
+
+impl Iterator for Args {
+    type Item = &'static CStr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.range.next().map(|i| arg(i).unwrap())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Args {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.range.next_back().map(|i| arg(i).unwrap())
+    }
+}
+
+impl ExactSizeIterator for Args {}
+
+/// Returns the arguments of the script.
+pub fn args() -> Args {
+    Args { range: 0..argv().0 }
+}
+
+/// Returns the arguments of the script as bytes, without the trailing nul.
+pub fn args_bytes() -> impl DoubleEndedIterator<Item = &'static [u8]> + ExactSizeIterator {
+    args().map(CStr::to_bytes)
+}
+
+/// Returns the arguments of the script as strings. Invalid utf-8 sequences are replaced with U+FFFD.
+pub fn args_string() -> Vec<String> {
+    args().map(|e| String::from(e.to_string_lossy())).collect()
+}
+
+/// Returns the argument at the given index.
+pub fn arg(index: usize) -> Option<&'static CStr> {
+    let (argc, argv) = argv();
+    if index >= argc {
+        return None;
+    }
+    // The index is in bounds and init guarantees the strings live for the whole execution.
+    Some(unsafe { CStr::from_ptr(*argv.add(index)) })
+}
@@ -1,19 +1,120 @@
-use crate::balloc::Allocator;
-use alloc::string::{String, ToString};
-use alloc::vec::Vec;
+use crate::balloc::{Algorithm, Allocator};
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU8, Ordering};
 
-#[global_allocator]
-pub static LALC: Allocator = Allocator {};
-pub static mut ARGS: Vec<String> = Vec::new();
+#[cfg_attr(feature = "allocator", global_allocator)]
+pub static LALC: Global = Global {};
 
+static OOM_EXIT_CODE: AtomicU8 = AtomicU8::new(102);
+
+/// Sets the exit code of the script when an allocation fails. The default is 102.
+pub fn set_oom_exit_code(code: i8) {
+    OOM_EXIT_CODE.store(code as u8, Ordering::Relaxed);
+}
+
+/// The buddy allocator, which reports the failure and exits the script instead of returning null when an allocation
+/// fails. Since it never returns null, fallible allocation APIs such as Vec::try_reserve exit the script as well
+/// instead of returning an error. A layout aligned to more than POOL_ALIGN is reported as unsupported rather than as
+/// out of memory.
+pub struct Global {}
+
+unsafe impl GlobalAlloc for Global {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { Allocator {}.alloc(layout) };
+        if ptr.is_null() {
+            oom(layout);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { Allocator {}.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { Allocator {}.realloc(ptr, layout, new_size) };
+        if new_ptr.is_null() {
+            oom(unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) });
+        }
+        new_ptr
+    }
+}
+
+// A message buffer on the stack, since the heap is exhausted. Messages that do not fit are truncated.
+struct StackBuf {
+    data: [u8; 128],
+    size: usize,
+}
+
+impl core::fmt::Write for StackBuf {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let n = s.len().min(self.data.len() - 1 - self.size);
+        self.data[self.size..self.size + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.size += n;
+        Ok(())
+    }
+}
+
+fn oom(layout: Layout) -> ! {
+    let mut buf = StackBuf { data: [0; 128], size: 0 };
+    let _ = if layout.align() > crate::balloc::POOL_ALIGN {
+        write!(
+            buf,
+            "unsupported alignment: requested {} bytes aligned to {} bytes, the heap aligns to at most {} bytes",
+            layout.size(),
+            layout.align(),
+            crate::balloc::POOL_ALIGN
+        )
+    } else {
+        write!(
+            buf,
+            "out of memory: requested {} bytes, available {} bytes, peak usage {} bytes",
+            layout.size(),
+            Algorithm::avail(),
+            Algorithm::peak()
+        )
+    };
+    crate::syscall::ecall(buf.data.as_ptr() as u64, 0, 0, 0, 0, 0, 0, 2177);
+    crate::syscall::exit(OOM_EXIT_CODE.load(Ordering::Relaxed) as i8 as u64)
+}
+
+// If the main thread panics it will terminate all your threads and end your program with code 101.
+// See: https://github.com/rust-lang/rust/blob/master/library/core/src/macros/panic.md
+#[cfg(feature = "panic-handler")]
+static PANIC_EXIT_CODE: AtomicU8 = AtomicU8::new(101);
+
+/// Sets the exit code of the script when it panics. The default is 101.
+#[cfg(feature = "panic-handler")]
+pub fn set_panic_exit_code(code: i8) {
+    PANIC_EXIT_CODE.store(code as u8, Ordering::Relaxed);
+}
+
+/// Reports a panic according to the panic features: panic-silent prints nothing, panic-message prints only the
+/// message, panic-verbose adds the location and the cycles consumed so far. Without any of them the panic info is
+/// printed as is.
+#[cfg(feature = "panic-handler")]
 #[panic_handler]
 pub fn panic_handler(i: &core::panic::PanicInfo) -> ! {
-    // If the main thread panics it will terminate all your threads and end your program with code 101.
-    // See: https://github.com/rust-lang/rust/blob/master/library/core/src/macros/panic.md
-    crate::syscall::debug(&i.to_string());
-    crate::syscall::exit(101)
+    #[cfg(not(any(feature = "panic-silent", feature = "panic-message", feature = "panic-verbose")))]
+    crate::syscall::debug(&alloc::format!("{}", i));
+    #[cfg(all(feature = "panic-message", not(feature = "panic-silent")))]
+    crate::syscall::debug(&alloc::format!("{}", i.message()));
+    #[cfg(all(feature = "panic-verbose", not(any(feature = "panic-silent", feature = "panic-message"))))]
+    {
+        let cycles = crate::syscall::current_cycles();
+        let message = match i.location() {
+            Some(l) => alloc::format!("{}:{}:{}: {} ({} cycles)", l.file(), l.line(), l.column(), i.message(), cycles),
+            None => alloc::format!("{} ({} cycles)", i.message(), cycles),
+        };
+        crate::syscall::debug(&message);
+    }
+    #[cfg(feature = "panic-silent")]
+    let _ = i;
+    crate::syscall::exit(PANIC_EXIT_CODE.load(Ordering::Relaxed) as i8 as u64)
 }
 
+#[cfg(feature = "start")]
 #[allow(clippy::missing_safety_doc)]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn _start() {
@@ -29,16 +130,18 @@ pub unsafe extern "C" fn _start() {
     }
 }
 
+#[cfg(feature = "start")]
+unsafe extern "C" {
+    fn main() -> u64;
+}
+
+/// Records argv and runs main. Its return value is left in a0, where _start passes it to the exit syscall.
+#[cfg(feature = "start")]
 #[allow(clippy::missing_safety_doc)]
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn _entry(argc: u64, argv: *const *const u8) {
+pub unsafe extern "C" fn _entry(argc: u64, argv: *const *const core::ffi::c_char) -> u64 {
     unsafe {
-        for i in 0..argc {
-            let argn = core::ffi::CStr::from_ptr(argv.add(i as usize).read());
-            let argn = String::from(argn.to_string_lossy());
-            #[allow(static_mut_refs)]
-            ARGS.push(argn);
-        }
-        core::arch::asm!("call main");
+        crate::env::init(argc as usize, argv);
+        main()
     }
 }
@@ -0,0 +1,386 @@
+//! Read and write traits for the pipes between scripts, in the spirit of std::io.
+//!
+//! The end of a stream is reached when the other end of the pipe is closed. Read::read reports it by returning zero,
+//! while read_exact and write_all report it as SysError::OtherEndClosed.
+
+use crate::process::Fd;
+use crate::syscall::SysError;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub const DEFAULT_BUF_SIZE: usize = 4096;
+
+pub trait Read {
+    /// Reads some bytes into buf, blocking until at least one byte is available. Returns zero at the end of the
+    /// stream.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SysError>;
+
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), SysError> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(SysError::OtherEndClosed),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads until the end of the stream, appending to out. Returns the number of bytes read.
+    fn read_to_end(&mut self, out: &mut Vec<u8>) -> Result<usize, SysError> {
+        let start = out.len();
+        let mut size = DEFAULT_BUF_SIZE;
+        loop {
+            let len = out.len();
+            out.resize(len + size, 0);
+            match self.read(&mut out[len..]) {
+                Ok(0) => {
+                    out.truncate(len);
+                    return Ok(len - start);
+                }
+                Ok(n) => {
+                    out.truncate(len + n);
+                    if n == size {
+                        size *= 2;
+                    }
+                }
+                Err(e) => {
+                    out.truncate(len);
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+pub trait Write {
+    /// Writes some bytes from buf and returns how many were written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, SysError>;
+
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), SysError> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(SysError::OtherEndClosed),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SysError> {
+        Ok(())
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SysError> {
+        (**self).read(buf)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, SysError> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), SysError> {
+        (**self).flush()
+    }
+}
+
+impl Read for Fd {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SysError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match crate::syscall::try_read(self.as_raw(), buf) {
+            Ok(n) => Ok(n as usize),
+            Err(SysError::OtherEndClosed) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Write for Fd {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, SysError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        Ok(crate::syscall::try_write(self.as_raw(), buf)? as usize)
+    }
+}
+
+/// Adds buffering to a reader, so that many small reads cost a single syscall.
+pub struct BufReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read> BufReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self { inner, buf: vec![0; capacity], pos: 0, filled: 0 }
+    }
+
+    /// Returns the buffered data, reading more from the inner reader if the buffer is empty. An empty slice means the
+    /// end of the stream.
+    pub fn fill_buf(&mut self) -> Result<&[u8], SysError> {
+        if self.pos == self.filled {
+            self.filled = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    /// Marks amount bytes of the buffered data as read.
+    pub fn consume(&mut self, amount: usize) {
+        self.pos = (self.pos + amount).min(self.filled);
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the inner reader. Any buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SysError> {
+        // Large reads bypass the buffer when it is empty.
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return self.inner.read(buf);
+        }
+        let data = self.fill_buf()?;
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+/// Adds buffering to a writer, so that many small writes cost a single syscall. The buffer is flushed on drop, where
+/// errors are ignored; call flush to observe them.
+pub struct BufWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> BufWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self { inner, buf: Vec::with_capacity(capacity) }
+    }
+
+    fn flush_buf(&mut self) -> Result<(), SysError> {
+        let r = self.inner.write_all(&self.buf);
+        self.buf.clear();
+        r
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Flushes the buffer and returns the inner writer.
+    pub fn into_inner(mut self) -> Result<W, SysError> {
+        self.flush_buf()?;
+        let this = core::mem::ManuallyDrop::new(self);
+        // The buffer is empty, and the writer is moved out exactly once since drop does not run.
+        let inner = unsafe { core::ptr::read(&this.inner) };
+        drop(unsafe { core::ptr::read(&this.buf) });
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, SysError> {
+        if self.buf.len() + buf.len() > self.buf.capacity() {
+            self.flush_buf()?;
+        }
+        if buf.len() >= self.buf.capacity() {
+            return self.inner.write(buf);
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), SysError> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads at most chunk bytes per call, and counts the calls.
+    struct Source {
+        data: Vec<u8>,
+        pos: usize,
+        chunk: usize,
+        reads: usize,
+    }
+
+    impl Source {
+        fn new(size: usize, chunk: usize) -> Self {
+            Self { data: (0..size).map(|i| i as u8).collect(), pos: 0, chunk, reads: 0 }
+        }
+    }
+
+    impl Read for Source {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, SysError> {
+            self.reads += 1;
+            let n = buf.len().min(self.chunk).min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    // Accepts at most chunk bytes per call, and counts the calls. A chunk of zero behaves like a closed pipe.
+    struct Sink {
+        data: Vec<u8>,
+        chunk: usize,
+        writes: usize,
+    }
+
+    impl Sink {
+        fn new(chunk: usize) -> Self {
+            Self { data: Vec::new(), chunk, writes: 0 }
+        }
+    }
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, SysError> {
+            self.writes += 1;
+            let n = buf.len().min(self.chunk);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_exact_partial_reads() {
+        let mut r = Source::new(100, 3);
+        let mut buf = [0; 10];
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(r.reads, 4);
+    }
+
+    #[test]
+    fn test_read_exact_end_of_stream() {
+        let mut r = Source::new(5, 3);
+        let mut buf = [0; 10];
+        assert_eq!(r.read_exact(&mut buf), Err(SysError::OtherEndClosed));
+    }
+
+    #[test]
+    fn test_read_to_end() {
+        let mut r = Source::new(10000, 1000);
+        let mut out = vec![0xff];
+        assert_eq!(r.read_to_end(&mut out).unwrap(), 10000);
+        assert_eq!(out.len(), 10001);
+        assert_eq!(out[0], 0xff);
+        assert_eq!(out[1..], r.data[..]);
+    }
+
+    #[test]
+    fn test_write_all_partial_writes() {
+        let mut w = Sink::new(2);
+        w.write_all(b"hello").unwrap();
+        assert_eq!(w.data, b"hello");
+        assert_eq!(w.writes, 3);
+    }
+
+    #[test]
+    fn test_write_all_end_of_stream() {
+        let mut w = Sink::new(0);
+        assert_eq!(w.write_all(b"hello"), Err(SysError::OtherEndClosed));
+    }
+
+    #[test]
+    fn test_buf_reader_small_reads() {
+        let mut r = BufReader::with_capacity(16, Source::new(40, 100));
+        let mut buf = [0; 4];
+        for i in 0..4 {
+            r.read_exact(&mut buf).unwrap();
+            assert_eq!(buf[0], i * 4);
+        }
+        assert_eq!(r.get_ref().reads, 1);
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [16, 17, 18, 19]);
+        assert_eq!(r.get_ref().reads, 2);
+    }
+
+    #[test]
+    fn test_buf_reader_large_read_bypasses_buffer() {
+        let mut r = BufReader::with_capacity(16, Source::new(40, 100));
+        let mut buf = [0; 32];
+        assert_eq!(r.read(&mut buf).unwrap(), 32);
+        assert_eq!(buf[31], 31);
+        assert_eq!(r.fill_buf().unwrap(), &[32, 33, 34, 35, 36, 37, 38, 39]);
+        r.consume(100);
+        assert_eq!(r.fill_buf().unwrap(), &[]);
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_buf_writer_buffers_until_flush() {
+        let mut sink = Sink::new(usize::MAX);
+        let mut w = BufWriter::with_capacity(16, &mut sink);
+        w.write_all(b"abc").unwrap();
+        w.write_all(b"def").unwrap();
+        assert_eq!(w.get_ref().writes, 0);
+        w.flush().unwrap();
+        assert_eq!(w.get_ref().writes, 1);
+        assert_eq!(w.get_ref().data, b"abcdef");
+    }
+
+    #[test]
+    fn test_buf_writer_flushes_on_drop() {
+        let mut sink = Sink::new(usize::MAX);
+        {
+            let mut w = BufWriter::with_capacity(16, &mut sink);
+            w.write_all(b"abc").unwrap();
+        }
+        assert_eq!(sink.data, b"abc");
+        assert_eq!(sink.writes, 1);
+    }
+
+    #[test]
+    fn test_buf_writer_into_inner() {
+        let mut w = BufWriter::with_capacity(16, Sink::new(usize::MAX));
+        w.write_all(b"abc").unwrap();
+        // A write larger than the buffer flushes the buffer first and goes straight through.
+        w.write_all(&[7; 20]).unwrap();
+        let sink = w.into_inner().unwrap();
+        assert_eq!(sink.data.len(), 23);
+        assert_eq!(sink.writes, 2);
+    }
+}
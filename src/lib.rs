@@ -1,8 +1,26 @@
 #![no_std]
 extern crate alloc;
+#[cfg(any(test, feature = "mock"))]
+extern crate std;
+#[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
+pub mod args;
 pub mod balloc;
 pub mod blake2b;
+#[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
+pub mod channel;
 pub mod core;
+#[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
+pub mod dlopen;
+#[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
+pub mod env;
+#[cfg(all(feature = "runtime", target_arch = "riscv64", not(feature = "mock")))]
 pub mod global;
+#[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
+pub mod io;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod molecule;
+#[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
+pub mod process;
+#[cfg(any(all(feature = "runtime", target_arch = "riscv64"), feature = "mock"))]
 pub mod syscall;
@@ -0,0 +1,445 @@
+//! A host side syscall backend, so that script logic can be unit tested with cargo test.
+//!
+//! With the mock feature enabled, syscall::ecall no longer traps into the ckb-vm. Instead each syscall is answered
+//! from a Context installed by the test. The context is kept per thread, so tests running in parallel do not see each
+//! other. The global allocator, panic handler and _start are left out in this mode, and the host's are used. The
+//! entry! macro still works: it declares an ordinary main function that tests can call, and env::args returns the
+//! arguments of the context.
+//!
+//! The script runs as a lock script by default, so its group is the inputs it locks. Call Context::type_group to run
+//! it as a type script, whose group is the inputs and outputs it types.
+//!
+//! ```
+//! use ckbes::core::{CellOutput, Script, Source};
+//!
+//! let script = Script::new([1; 32], ckbes::core::SCRIPT_HASH_TYPE_TYPE, vec![]);
+//! let cell = CellOutput::new(100, script.clone(), None);
+//! ckbes::mock::Context::new(script).input(cell.clone(), vec![1, 2, 3]).output(cell, vec![]).install();
+//! assert_eq!(ckbes::syscall::iter_cells(Source::GroupInput).count(), 1);
+//! assert_eq!(ckbes::syscall::load_cell_data(0, Source::GroupInput), vec![1, 2, 3]);
+//! ```
+//!
+//! Spawn, exec and the pipe syscalls are not supported and panic. Exit panics as well, with the exit code in the
+//! message.
+
+use crate::core::{CellInput, CellOutput, Header, OutPoint, Script, Transaction};
+use crate::syscall::SysError;
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::cell::RefCell;
+
+/// Whether the running script is a lock script or a type script, which decides the cells of its group.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ScriptGroupType {
+    /// The group is made of the inputs whose lock script is the running script. Lock scripts do not run on outputs.
+    #[default]
+    Lock,
+    /// The group is made of the inputs and outputs whose type script is the running script.
+    Type,
+}
+
+/// The transaction a script runs in, along with the resolved cells and headers the vm would otherwise provide.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    /// The running script.
+    pub script: Script,
+    pub group_type: ScriptGroupType,
+    /// The arguments of the script, returned by env::args.
+    pub args: Vec<Vec<u8>>,
+    pub tx: Transaction,
+    /// The cells and data spent by tx.raw.inputs, in the same order.
+    pub inputs: Vec<(CellOutput, Vec<u8>)>,
+    /// The cells and data referenced by tx.raw.cell_deps, in the same order.
+    pub cell_deps: Vec<(CellOutput, Vec<u8>)>,
+    /// The headers referenced by tx.raw.header_deps, in the same order.
+    pub header_deps: Vec<Header>,
+    /// Messages written with syscall::debug.
+    pub debug: Vec<String>,
+}
+
+impl Context {
+    pub fn new(script: Script) -> Self {
+        Self { script, ..Default::default() }
+    }
+
+    /// Runs the script as a type script instead of a lock script.
+    pub fn type_group(mut self) -> Self {
+        self.group_type = ScriptGroupType::Type;
+        self
+    }
+
+    /// Adds an argument. It must not contain a nul byte.
+    pub fn arg(mut self, arg: &[u8]) -> Self {
+        self.args.push(arg.to_vec());
+        self
+    }
+
+    /// Adds an input spending the given cell. The out point is made up from the input index.
+    pub fn input(mut self, cell: CellOutput, data: Vec<u8>) -> Self {
+        let out_point = OutPoint::new([0; 32], self.inputs.len() as u32);
+        self.tx.raw.inputs.push(CellInput::new(0, out_point));
+        self.inputs.push((cell, data));
+        self
+    }
+
+    pub fn output(mut self, cell: CellOutput, data: Vec<u8>) -> Self {
+        self.tx.raw.outputs.push(cell);
+        self.tx.raw.outputs_data.push(data);
+        self
+    }
+
+    /// Adds a cell dep referencing the given cell. The out point is made up from the cell dep index.
+    pub fn cell_dep(mut self, cell: CellOutput, data: Vec<u8>) -> Self {
+        let out_point = OutPoint::new([0; 32], self.cell_deps.len() as u32);
+        self.tx.raw.cell_deps.push(crate::core::CellDep::new(out_point, 0));
+        self.cell_deps.push((cell, data));
+        self
+    }
+
+    pub fn header_dep(mut self, header: Header) -> Self {
+        self.tx.raw.header_deps.push(crate::blake2b::blake2b_256(header.molecule()));
+        self.header_deps.push(header);
+        self
+    }
+
+    pub fn witness(mut self, witness: Vec<u8>) -> Self {
+        self.tx.witnesses.push(witness);
+        self
+    }
+
+    /// Makes this context answer the syscalls of the current thread.
+    pub fn install(self) {
+        // The strings of argv must live as long as the thread may read them, so they are leaked.
+        let argv: Vec<*const core::ffi::c_char> = self
+            .args
+            .iter()
+            .map(|e| std::ffi::CString::new(e.clone()).unwrap().into_raw() as *const core::ffi::c_char)
+            .collect();
+        let argc = argv.len();
+        unsafe { crate::env::init(argc, argv.leak().as_ptr()) };
+        CONTEXT.with(|c| *c.borrow_mut() = self);
+    }
+
+    fn in_group(&self, cell: &CellOutput) -> bool {
+        let hash = self.script.hash();
+        match self.group_type {
+            ScriptGroupType::Lock => cell.lock.hash() == hash,
+            ScriptGroupType::Type => cell.type_.as_ref().map(|e| e.hash()) == Some(hash),
+        }
+    }
+
+    fn group_inputs(&self) -> Vec<usize> {
+        (0..self.inputs.len()).filter(|&i| self.in_group(&self.inputs[i].0)).collect()
+    }
+
+    fn group_outputs(&self) -> Vec<usize> {
+        if self.group_type == ScriptGroupType::Lock {
+            return Vec::new();
+        }
+        (0..self.tx.raw.outputs.len()).filter(|&i| self.in_group(&self.tx.raw.outputs[i])).collect()
+    }
+
+    fn cell(&self, index: u64, source: u64) -> Result<(CellOutput, Vec<u8>), SysError> {
+        let index = index as usize;
+        let found = match source {
+            crate::core::SOURCE_INPUT => self.inputs.get(index).cloned(),
+            crate::core::SOURCE_OUTPUT => {
+                self.tx.raw.outputs.get(index).map(|e| (e.clone(), self.tx.raw.outputs_data[index].clone()))
+            }
+            crate::core::SOURCE_CELL_DEP => self.cell_deps.get(index).cloned(),
+            crate::core::SOURCE_GROUP_INPUT => {
+                return self
+                    .group_inputs()
+                    .get(index)
+                    .map_or(Err(SysError::IndexOutOfBound), |&i| self.cell(i as u64, crate::core::SOURCE_INPUT));
+            }
+            crate::core::SOURCE_GROUP_OUTPUT => {
+                return self
+                    .group_outputs()
+                    .get(index)
+                    .map_or(Err(SysError::IndexOutOfBound), |&i| self.cell(i as u64, crate::core::SOURCE_OUTPUT));
+            }
+            _ => None,
+        };
+        found.ok_or(SysError::IndexOutOfBound)
+    }
+
+    fn cell_input(&self, index: u64, source: u64) -> Result<CellInput, SysError> {
+        let index = match source {
+            crate::core::SOURCE_INPUT => index as usize,
+            crate::core::SOURCE_GROUP_INPUT => {
+                *self.group_inputs().get(index as usize).ok_or(SysError::IndexOutOfBound)?
+            }
+            _ => return Err(SysError::IndexOutOfBound),
+        };
+        self.tx.raw.inputs.get(index).cloned().ok_or(SysError::IndexOutOfBound)
+    }
+
+    fn header(&self, index: u64, source: u64) -> Result<Header, SysError> {
+        match source {
+            crate::core::SOURCE_HEADER_DEP => {
+                self.header_deps.get(index as usize).cloned().ok_or(SysError::IndexOutOfBound)
+            }
+            // The block of an input or a cell dep is not known to the mock.
+            crate::core::SOURCE_INPUT | crate::core::SOURCE_CELL_DEP | crate::core::SOURCE_GROUP_INPUT => {
+                self.cell(index, source)?;
+                Err(SysError::ItemMissing)
+            }
+            _ => Err(SysError::IndexOutOfBound),
+        }
+    }
+
+    fn witness_at(&self, index: u64, source: u64) -> Result<Vec<u8>, SysError> {
+        let index = match source {
+            crate::core::SOURCE_INPUT | crate::core::SOURCE_OUTPUT => index as usize,
+            crate::core::SOURCE_GROUP_INPUT => {
+                *self.group_inputs().get(index as usize).ok_or(SysError::IndexOutOfBound)?
+            }
+            crate::core::SOURCE_GROUP_OUTPUT => {
+                *self.group_outputs().get(index as usize).ok_or(SysError::IndexOutOfBound)?
+            }
+            _ => return Err(SysError::IndexOutOfBound),
+        };
+        self.tx.witnesses.get(index).cloned().ok_or(SysError::IndexOutOfBound)
+    }
+}
+
+std::thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::new(Context::default());
+}
+
+/// Gives access to the context of the current thread, for example to inspect the debug messages.
+pub fn with<R>(f: impl FnOnce(&mut Context) -> R) -> R {
+    CONTEXT.with(|c| f(&mut c.borrow_mut()))
+}
+
+/// Copies data into the buffer the way the vm does: at most *len bytes starting at offset are written, and *len is
+/// set to the number of bytes available from offset.
+fn store(addr: u64, len: u64, offset: u64, data: &[u8]) -> u64 {
+    let len = len as *mut u64;
+    let offset = (offset as usize).min(data.len());
+    let full = data.len() - offset;
+    unsafe {
+        let real = full.min(*len as usize);
+        core::ptr::copy_nonoverlapping(data[offset..].as_ptr(), addr as *mut u8, real);
+        *len = full as u64;
+    }
+    0
+}
+
+fn answer(addr: u64, len: u64, offset: u64, data: Result<Vec<u8>, SysError>) -> u64 {
+    match data {
+        Ok(data) => store(addr, len, offset, &data),
+        Err(e) => e.code(),
+    }
+}
+
+fn cell_field(cell: &CellOutput, data: &[u8], field: u64) -> Result<Vec<u8>, SysError> {
+    let script_size = |e: &Script| 32 + 1 + e.args.len() as u64;
+    match field {
+        0 => Ok(cell.capacity.to_le_bytes().to_vec()),
+        1 => Ok(crate::blake2b::blake2b_256(data).to_vec()),
+        2 => Ok(cell.lock.molecule()),
+        3 => Ok(cell.lock.hash().to_vec()),
+        4 => cell.type_.as_ref().map(|e| e.molecule()).ok_or(SysError::ItemMissing),
+        5 => cell.type_.as_ref().map(|e| e.hash().to_vec()).ok_or(SysError::ItemMissing),
+        6 => {
+            let size = 8 + data.len() as u64 + script_size(&cell.lock) + cell.type_.as_ref().map_or(0, script_size);
+            Ok((size * 100_000_000).to_le_bytes().to_vec())
+        }
+        _ => Err(SysError::ItemMissing),
+    }
+}
+
+fn header_field(header: &Header, field: u64) -> Result<Vec<u8>, SysError> {
+    let epoch = header.raw.epoch;
+    let number = epoch & 0xffffff;
+    let index = (epoch >> 24) & 0xffff;
+    let length = (epoch >> 40) & 0xffff;
+    match field {
+        0 => Ok(number.to_le_bytes().to_vec()),
+        1 => Ok((header.raw.number - index).to_le_bytes().to_vec()),
+        2 => Ok(length.to_le_bytes().to_vec()),
+        _ => Err(SysError::ItemMissing),
+    }
+}
+
+fn input_field(input: &CellInput, field: u64) -> Result<Vec<u8>, SysError> {
+    match field {
+        0 => Ok(input.previous_output.molecule()),
+        1 => Ok(input.since.to_le_bytes().to_vec()),
+        _ => Err(SysError::ItemMissing),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn ecall(a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, _: u64, a7: u64) -> u64 {
+    with(|c| match a7 {
+        93 => panic!("ckbes: exit with code {}", a0 as i8),
+        2041 => 2,
+        2042 => 0,
+        2051 => answer(a0, a1, a2, Ok(c.tx.molecule())),
+        2052 => answer(a0, a1, a2, Ok(c.script.molecule())),
+        2061 => answer(a0, a1, a2, Ok(c.tx.hash().to_vec())),
+        2062 => answer(a0, a1, a2, Ok(c.script.hash().to_vec())),
+        2071 => answer(a0, a1, a2, c.cell(a3, a4).map(|e| e.0.molecule())),
+        2072 => answer(a0, a1, a2, c.header(a3, a4).map(|e| e.molecule())),
+        2073 => answer(a0, a1, a2, c.cell_input(a3, a4).map(|e| e.molecule())),
+        2074 => answer(a0, a1, a2, c.witness_at(a3, a4)),
+        2081 => answer(a0, a1, a2, c.cell(a3, a4).and_then(|e| cell_field(&e.0, &e.1, a5))),
+        2082 => answer(a0, a1, a2, c.header(a3, a4).and_then(|e| header_field(&e, a5))),
+        2083 => answer(a0, a1, a2, c.cell_input(a3, a4).and_then(|e| input_field(&e, a5))),
+        2091 => match c.cell(a4, a5) {
+            // The code is copied but can not be run on the host.
+            Ok((_, data)) => {
+                let offset = (a2 as usize).min(data.len());
+                let end = (offset + a3 as usize).min(data.len());
+                if end - offset > a1 as usize {
+                    return SysError::SliceOutOfBound.code();
+                }
+                unsafe { core::ptr::copy_nonoverlapping(data[offset..end].as_ptr(), a0 as *mut u8, end - offset) };
+                0
+            }
+            Err(e) => e.code(),
+        },
+        2092 => answer(a0, a1, a2, c.cell(a3, a4).map(|e| e.1)),
+        2104 => answer(a0, a1, a2, c.cell(a3, a4).and(Err(SysError::ItemMissing))),
+        2177 => {
+            let msg = unsafe { core::ffi::CStr::from_ptr(a0 as *const core::ffi::c_char) };
+            c.debug.push(String::from(msg.to_string_lossy()));
+            0
+        }
+        2603 => 0,
+        2607 => {
+            unsafe { *(a1 as *mut u64) = 0 };
+            0
+        }
+        _ => panic!("ckbes: syscall {} is not supported by the mock", a7),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{CellField, HeaderField, HeaderSource, RawHeader, Source};
+    use crate::syscall::*;
+    use alloc::vec;
+
+    fn script(n: u8) -> Script {
+        Script::new([n; 32], crate::core::SCRIPT_HASH_TYPE_TYPE, vec![n])
+    }
+
+    // Inputs 0 and 2 are locked by script 1, inputs 1 and 2 and output 1 have script 1 as type script.
+    fn context() -> Context {
+        Context::new(script(1))
+            .input(CellOutput::new(1, script(1), None), vec![0])
+            .input(CellOutput::new(2, script(2), Some(script(1))), vec![1])
+            .input(CellOutput::new(3, script(1), Some(script(1))), vec![2])
+            .output(CellOutput::new(4, script(2), None), vec![3])
+            .output(CellOutput::new(5, script(2), Some(script(1))), vec![4])
+            .witness(vec![10])
+            .witness(vec![11])
+            .witness(vec![12])
+    }
+
+    #[test]
+    fn test_lock_group() {
+        context().install();
+        assert_eq!(iter_cells(Source::GroupInput).map(|e| e.capacity).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(iter_cells(Source::GroupOutput).count(), 0);
+        assert_eq!(load_input_out_point(1, Source::GroupInput).index, 2);
+    }
+
+    #[test]
+    fn test_type_group() {
+        context().type_group().install();
+        assert_eq!(iter_cells(Source::GroupInput).map(|e| e.capacity).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(iter_cells(Source::GroupOutput).map(|e| e.capacity).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(iter_cell_data(Source::GroupOutput).collect::<Vec<_>>(), vec![vec![4]]);
+    }
+
+    #[test]
+    fn test_group_witness() {
+        context().type_group().install();
+        assert_eq!(load_witness(0, Source::GroupInput), vec![11]);
+        assert_eq!(load_witness(1, Source::GroupInput), vec![12]);
+        assert_eq!(load_witness(0, Source::GroupOutput), vec![11]);
+        assert_eq!(try_load_witness(1, Source::GroupOutput), Err(SysError::IndexOutOfBound));
+        assert_eq!(load_witness(2, Source::Input), vec![12]);
+    }
+
+    #[test]
+    fn test_cell_fields() {
+        context().install();
+        // 8 bytes of capacity, 1 byte of data and two scripts of 32 + 1 + 1 bytes.
+        assert_eq!(load_cell_occupied_capacity(2, Source::Input), (8 + 1 + 34 + 34) * 100_000_000);
+        assert_eq!(load_cell_occupied_capacity(0, Source::Input), (8 + 1 + 34) * 100_000_000);
+        assert_eq!(load_cell_type_hash(0, Source::Input), None);
+        assert_eq!(load_cell_type_hash(1, Source::Input), Some(script(1).hash()));
+        let types: Vec<_> = iter_cells_by_field(Source::Input, CellField::TypeHash).collect();
+        assert_eq!(types, vec![None, Some(script(1).hash().to_vec()), Some(script(1).hash().to_vec())]);
+        assert_eq!(iter_cell_types(Source::Output).collect::<Vec<_>>(), vec![None, Some(script(1))]);
+    }
+
+    #[test]
+    fn test_header_fields() {
+        // Block 1000 is the 10th block of epoch 7, which is 1800 blocks long.
+        let raw = RawHeader { number: 1000, epoch: 7 | (10 << 24) | (1800 << 40), ..Default::default() };
+        context().header_dep(Header::new(raw, 0)).install();
+        assert_eq!(load_header_epoch_number(0, HeaderSource::HeaderDep), 7);
+        assert_eq!(load_header_epoch_start_block_number(0, HeaderSource::HeaderDep), 990);
+        assert_eq!(load_header_epoch_length(0, HeaderSource::HeaderDep), 1800);
+        assert_eq!(try_load_header(1, HeaderSource::HeaderDep), Err(SysError::IndexOutOfBound));
+    }
+
+    #[test]
+    fn test_iter_headers() {
+        let raw = RawHeader { number: 1000, epoch: 7 | (10 << 24) | (1800 << 40), ..Default::default() };
+        context().header_dep(Header::new(raw.clone(), 0)).install();
+        // The blocks of the inputs are not in the header deps.
+        assert_eq!(iter_headers(HeaderSource::Input).collect::<Vec<_>>(), vec![None, None, None]);
+        assert_eq!(iter_headers(HeaderSource::HeaderDep).collect::<Vec<_>>(), vec![Some(Header::new(raw, 0))]);
+        let numbers: Vec<_> = iter_headers_by_field(HeaderSource::GroupInput, HeaderField::EpochNumber).collect();
+        assert_eq!(numbers, vec![None, None]);
+        let numbers: Vec<_> = iter_headers_by_field(HeaderSource::HeaderDep, HeaderField::EpochNumber).collect();
+        assert_eq!(numbers, vec![Some(7u64.to_le_bytes().to_vec())]);
+    }
+
+    #[test]
+    fn test_partial_load() {
+        Context::new(script(1)).input(CellOutput::new(1, script(1), None), (0..10).collect()).install();
+        let mut buf = [0; 4];
+        assert_eq!(load_cell_data_range(0, Source::Input, 3, &mut buf), 7);
+        assert_eq!(buf, [3, 4, 5, 6]);
+        assert_eq!(load_cell_data_range(0, Source::Input, 8, &mut buf), 2);
+        assert_eq!(buf[..2], [8, 9]);
+        assert_eq!(load_cell_data_range(0, Source::Input, 20, &mut buf), 0);
+        assert_eq!(load_cell_data_into(0, Source::Input, &mut buf), (4, 10));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        use crate::process::exit_code;
+        assert_eq!(exit_code::<i8>(Ok(())), 0);
+        assert_eq!(exit_code(Err(5i8)), 5);
+        assert_eq!(exit_code(Err(0i8)) as i8, -1);
+        assert_eq!(exit_code(Err(SysError::ItemMissing)), 2);
+        assert_eq!(exit_code(Err(SysError::Unknown(256))) as i8, -1);
+        assert_eq!(exit_code(Err(SysError::Unknown(0))) as i8, -1);
+    }
+
+    #[test]
+    fn test_entry() {
+        fn program_entry(args: &[String]) -> Result<(), i8> {
+            debug(&args.join(" "));
+            if args.is_empty() { Err(3) } else { Ok(()) }
+        }
+        crate::entry!(program_entry);
+        Context::new(script(1)).arg(b"hello").arg(&[0xff]).install();
+        assert_eq!(main(), 0);
+        assert_eq!(crate::env::args_bytes().collect::<Vec<_>>(), vec![&b"hello"[..], &[0xff]]);
+        assert_eq!(with(|c| c.debug.clone()), vec![String::from("hello \u{fffd}")]);
+        Context::new(script(1)).install();
+        assert_eq!(main(), 3);
+    }
+}
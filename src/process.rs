@@ -0,0 +1,243 @@
+//! Spawning child scripts, modeled after std::process.
+//!
+//! File descriptors are owned by the Fd type and closed when dropped. The child receives its pipes through
+//! syscall::inherited_fds, in this order: the read end of its stdin, the write end of its stdout, then the fds
+//! passed with Command::inherit. Pipes that were not requested are simply left out.
+
+use crate::core::{Place, Source};
+use crate::syscall::SysError;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// An owned file descriptor, closed on drop.
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub struct Fd(u64);
+
+impl Fd {
+    pub fn from_raw(fd: u64) -> Self {
+        Self(fd)
+    }
+
+    pub fn as_raw(&self) -> u64 {
+        self.0
+    }
+
+    /// Gives up the ownership of the file descriptor without closing it.
+    pub fn into_raw(self) -> u64 {
+        let fd = self.0;
+        core::mem::forget(self);
+        fd
+    }
+
+    /// Closes the file descriptor, reporting the error that drop would ignore.
+    pub fn close(self) -> Result<(), SysError> {
+        crate::syscall::try_close(self.into_raw())
+    }
+}
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        let _ = crate::syscall::try_close(self.0);
+    }
+}
+
+/// Creates a pipe, returning the read end and the write end.
+pub fn pipe() -> Result<(Fd, Fd), SysError> {
+    let fds = crate::syscall::try_pipe()?;
+    Ok((Fd(fds[0]), Fd(fds[1])))
+}
+
+/// Returns the file descriptors passed to the current script by its parent.
+pub fn inherited_fds() -> Result<Vec<Fd>, SysError> {
+    Ok(crate::syscall::try_inherited_fds()?.into_iter().map(Fd).collect())
+}
+
+enum Target {
+    Index(u64, Source),
+    Hash([u8; 32], u8),
+}
+
+/// A builder for spawning a child script.
+pub struct Command {
+    target: Target,
+    place: Place,
+    bounds: Option<(u32, u32)>,
+    args: Vec<String>,
+    fds: Vec<Fd>,
+    stdin: bool,
+    stdout: bool,
+}
+
+impl Command {
+    /// Runs the program at the given index and source.
+    pub fn new(index: u64, source: Source) -> Self {
+        Self::with_target(Target::Index(index, source))
+    }
+
+    /// Runs the cell dep found by code hash and hash type, see syscall::find_cell_dep.
+    pub fn from_code_hash(code_hash: [u8; 32], hash_type: u8) -> Self {
+        Self::with_target(Target::Hash(code_hash, hash_type))
+    }
+
+    fn with_target(target: Target) -> Self {
+        Self {
+            target,
+            place: Place::CellData,
+            bounds: None,
+            args: Vec::new(),
+            fds: Vec::new(),
+            stdin: false,
+            stdout: false,
+        }
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(String::from(arg));
+        self
+    }
+
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.args.extend(args.iter().map(|e| String::from(*e)));
+        self
+    }
+
+    /// Passes a typed argument, see the args module.
+    pub fn arg_typed<T: crate::args::Arg>(mut self, arg: &T) -> Self {
+        self.args.push(crate::args::encode(arg));
+        self
+    }
+
+    /// Passes a file descriptor to the child. Its ownership moves to the child once spawned.
+    pub fn inherit(mut self, fd: Fd) -> Self {
+        self.fds.push(fd);
+        self
+    }
+
+    pub fn place(mut self, place: Place) -> Self {
+        self.place = place;
+        self
+    }
+
+    /// Runs only length bytes of the program starting at offset. A length of zero means up to the end.
+    pub fn bounds(mut self, offset: u32, length: u32) -> Self {
+        self.bounds = Some((offset, length));
+        self
+    }
+
+    /// Creates a pipe whose write end is available as Child::stdin.
+    pub fn stdin(mut self, piped: bool) -> Self {
+        self.stdin = piped;
+        self
+    }
+
+    /// Creates a pipe whose read end is available as Child::stdout.
+    pub fn stdout(mut self, piped: bool) -> Self {
+        self.stdout = piped;
+        self
+    }
+
+    pub fn spawn(self) -> Result<Child, SysError> {
+        let (index, source) = match self.target {
+            Target::Index(index, source) => (index, source),
+            Target::Hash(code_hash, hash_type) => {
+                (crate::syscall::try_find_cell_dep(code_hash, hash_type)?, Source::CellDep)
+            }
+        };
+        let mut fds = Vec::new();
+        let stdin = if self.stdin {
+            let (r, w) = pipe()?;
+            fds.push(r);
+            Some(w)
+        } else {
+            None
+        };
+        let stdout = if self.stdout {
+            let (r, w) = pipe()?;
+            fds.push(w);
+            Some(r)
+        } else {
+            None
+        };
+        fds.extend(self.fds);
+        let args: Vec<&str> = self.args.iter().map(|e| e.as_str()).collect();
+        let raw: Vec<u64> = fds.iter().map(|e| e.as_raw()).collect();
+        let pid = crate::syscall::try_spawn(index, source, self.place, self.bounds, &args, &raw)?;
+        // The child now owns these file descriptors.
+        for fd in fds {
+            fd.into_raw();
+        }
+        Ok(Child { pid, stdin, stdout })
+    }
+}
+
+/// A spawned child script.
+pub struct Child {
+    pub pid: u64,
+    pub stdin: Option<Fd>,
+    pub stdout: Option<Fd>,
+}
+
+impl Child {
+    pub fn id(&self) -> u64 {
+        self.pid
+    }
+
+    /// Waits for the child to exit and returns its exit code. The stdin pipe is closed first, so a child reading
+    /// until the end of its input does not block forever.
+    pub fn wait(&mut self) -> Result<i8, SysError> {
+        drop(self.stdin.take());
+        let code = crate::syscall::try_wait(self.pid)?;
+        Ok(code as u8 as i8)
+    }
+}
+
+/// The exit code of an error that converts to zero, which ckb would take for success.
+pub const EXIT_ZERO_ERROR: i8 = -1;
+
+/// Maps the result of a script to its exit code: zero for success, and the error converted to i8 otherwise. An error
+/// converting to zero exits with EXIT_ZERO_ERROR, so a failing script never passes.
+pub fn exit_code<E: Into<i8>>(result: Result<(), E>) -> u64 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => match e.into() {
+            0 => EXIT_ZERO_ERROR as u64,
+            code => code as u64,
+        },
+    }
+}
+
+/// Declares the entry point of a script. The given function receives the script arguments and returns a result, whose
+/// error becomes the exit code of the script. Arguments are converted lossily to strings; use ckbes::env::args for the
+/// original bytes.
+///
+/// ```ignore
+/// ckbes::entry!(program_entry);
+///
+/// fn program_entry(args: &[String]) -> Result<(), i8> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(feature = "mock"))]
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn main() -> u64 {
+            let args = $crate::env::args_string();
+            $crate::process::exit_code($main(&args))
+        }
+    };
+}
+
+/// Declares the entry point of a script. With the mock feature main is an ordinary function, so host tests can call
+/// it after installing a context.
+#[cfg(feature = "mock")]
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        pub fn main() -> u64 {
+            let args = $crate::env::args_string();
+            $crate::process::exit_code($main(&args))
+        }
+    };
+}
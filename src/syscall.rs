@@ -1,9 +1,109 @@
 use alloc::ffi::CString;
 use alloc::vec::Vec;
 
+/// Error codes returned by the ckb-vm syscalls.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SysError {
+    /// The index is out of bound.
+    IndexOutOfBound,
+    /// The field is missing for the target.
+    ItemMissing,
+    /// The buffer length is not enough, or the bounds are out of range.
+    SliceOutOfBound,
+    /// The data is not in the expected format.
+    WrongFormat,
+    /// The process to wait for does not exist, or was already waited.
+    WaitFailure,
+    /// The file descriptor is invalid, or not owned by the current process.
+    InvalidFd,
+    /// The other end of the pipe has been closed.
+    OtherEndClosed,
+    /// The maximum number of spawned vms has been reached.
+    MaxVmsSpawned,
+    /// The maximum number of file descriptors has been reached.
+    MaxFdsCreated,
+    /// An error code unknown to ckbes.
+    Unknown(u64),
+}
+
+impl SysError {
+    pub fn code(&self) -> u64 {
+        match self {
+            SysError::IndexOutOfBound => 1,
+            SysError::ItemMissing => 2,
+            SysError::SliceOutOfBound => 3,
+            SysError::WrongFormat => 4,
+            SysError::WaitFailure => 5,
+            SysError::InvalidFd => 6,
+            SysError::OtherEndClosed => 7,
+            SysError::MaxVmsSpawned => 8,
+            SysError::MaxFdsCreated => 9,
+            SysError::Unknown(code) => *code,
+        }
+    }
+
+    pub fn from_code(code: u64) -> Result<(), SysError> {
+        match code {
+            0 => Ok(()),
+            1 => Err(SysError::IndexOutOfBound),
+            2 => Err(SysError::ItemMissing),
+            3 => Err(SysError::SliceOutOfBound),
+            4 => Err(SysError::WrongFormat),
+            5 => Err(SysError::WaitFailure),
+            6 => Err(SysError::InvalidFd),
+            7 => Err(SysError::OtherEndClosed),
+            8 => Err(SysError::MaxVmsSpawned),
+            9 => Err(SysError::MaxFdsCreated),
+            code => Err(SysError::Unknown(code)),
+        }
+    }
+}
+
+/// Converts to the error code as an exit code. Unknown codes that do not fit in 1..=127 become -1.
+impl From<SysError> for i8 {
+    fn from(e: SysError) -> i8 {
+        match e.code() {
+            code @ 1..=127 => code as i8,
+            _ => -1,
+        }
+    }
+}
+
+/// Iterates over a transaction source, loading items with increasing index until the vm reports index out of bound.
+/// Any other error is treated as fatal and panics.
+pub struct QueryIter<F, S = crate::core::Source> {
+    load: F,
+    index: u64,
+    source: S,
+}
+
+impl<F, S> QueryIter<F, S> {
+    pub fn new(load: F, source: S) -> Self {
+        Self { load, index: 0, source }
+    }
+}
+
+impl<F, S, T> Iterator for QueryIter<F, S>
+where
+    S: Copy,
+    F: Fn(u64, S) -> Result<T, SysError>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match (self.load)(self.index, self.source) {
+            Ok(item) => {
+                self.index += 1;
+                Some(item)
+            }
+            Err(SysError::IndexOutOfBound) => None,
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+}
+
 pub fn close(fd: u64) {
-    let ret = ecall(fd, 0, 0, 0, 0, 0, 0, 2608);
-    assert!(ret == 0);
+    try_close(fd).unwrap()
 }
 
 pub fn current_cycles() -> u64 {
@@ -16,6 +116,7 @@ pub fn debug(msg: &str) -> u64 {
     ecall(data.as_ptr() as u64, 0, 0, 0, 0, 0, 0, 2177)
 }
 
+#[cfg(not(feature = "mock"))]
 #[allow(clippy::too_many_arguments)]
 pub fn ecall(mut a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, a6: u64, a7: u64) -> u64 {
     unsafe {
@@ -34,12 +135,33 @@ pub fn ecall(mut a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, a6: u64,
     a0
 }
 
-pub fn exec(index: u64, source: u64, args: &[&str]) -> ! {
-    let args_vec: Vec<u64> = args.iter().map(|e| CString::new(*e).unwrap().as_c_str().as_ptr() as u64).collect();
-    let args_ptr = args_vec.as_ptr() as u64;
-    let ret = ecall(index, source, 0, 0, args.len() as u64, args_ptr, 0, 2043);
-    assert!(ret == 0);
-    panic!();
+#[cfg(feature = "mock")]
+#[allow(clippy::too_many_arguments)]
+pub fn ecall(a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, a6: u64, a7: u64) -> u64 {
+    crate::mock::ecall(a0, a1, a2, a3, a4, a5, a6, a7)
+}
+
+/// Packs (offset, length) into the bounds argument of exec and spawn. No bounds means the whole program.
+fn encode_bounds(bounds: Option<(u32, u32)>) -> u64 {
+    match bounds {
+        Some((offset, length)) => ((offset as u64) << 32) | length as u64,
+        None => 0,
+    }
+}
+
+pub fn exec(
+    index: u64,
+    source: crate::core::Source,
+    place: crate::core::Place,
+    bounds: Option<(u32, u32)>,
+    args: &[&str],
+) -> ! {
+    panic!("{:?}", try_exec(index, source, place, bounds, args));
+}
+
+/// Executes the cell dep found by code hash and hash type, see find_cell_dep.
+pub fn exec_cell(code_hash: [u8; 32], hash_type: u8, args: &[&str]) -> ! {
+    panic!("{:?}", try_exec_cell(code_hash, hash_type, args));
 }
 
 pub fn exit(code: u64) -> ! {
@@ -47,165 +169,842 @@ pub fn exit(code: u64) -> ! {
     panic!();
 }
 
+/// Finds the index of the cell dep that a script with this code hash and hash type refers to. The data hash types
+/// match the hash of the cell data, and the type hash type matches the hash of the type script of the cell.
+pub fn find_cell_dep(code_hash: [u8; 32], hash_type: u8) -> u64 {
+    try_find_cell_dep(code_hash, hash_type).unwrap()
+}
+
 pub fn inherited_fds() -> Vec<u64> {
+    try_inherited_fds().unwrap()
+}
+
+pub fn iter_cell_data(source: crate::core::Source) -> impl Iterator<Item = Vec<u8>> {
+    QueryIter::new(try_load_cell_data, source)
+}
+
+/// Iterates over the type script hashes of the cells, which are none for cells without a type script.
+pub fn iter_cell_type_hashes(source: crate::core::Source) -> impl Iterator<Item = Option<[u8; 32]>> {
+    QueryIter::new(try_load_cell_type_hash, source)
+}
+
+/// Iterates over the type scripts of the cells, which are none for cells without a type script.
+pub fn iter_cell_types(source: crate::core::Source) -> impl Iterator<Item = Option<crate::core::Script>> {
+    QueryIter::new(try_load_cell_type, source)
+}
+
+pub fn iter_cells(source: crate::core::Source) -> impl Iterator<Item = crate::core::CellOutput> {
+    QueryIter::new(try_load_cell, source)
+}
+
+/// Iterates over a field of the cells. The type and type hash fields are missing for cells without a type script,
+/// which is reported as none instead of ending the iteration.
+pub fn iter_cells_by_field(
+    source: crate::core::Source,
+    field: crate::core::CellField,
+) -> impl Iterator<Item = Option<Vec<u8>>> {
+    QueryIter::new(
+        move |index, source| match try_load_cell_by_field(index, source, field) {
+            Ok(data) => Ok(Some(data)),
+            Err(SysError::ItemMissing) => Ok(None),
+            Err(e) => Err(e),
+        },
+        source,
+    )
+}
+
+/// Iterates over the headers of the blocks the cells are in. The header of an input or a cell dep is missing when its
+/// block is not in the header deps, which is reported as none instead of ending the iteration.
+pub fn iter_headers(source: crate::core::HeaderSource) -> impl Iterator<Item = Option<crate::core::Header>> {
+    QueryIter::new(
+        |index, source| match try_load_header(index, source) {
+            Ok(data) => Ok(Some(data)),
+            Err(SysError::ItemMissing) => Ok(None),
+            Err(e) => Err(e),
+        },
+        source,
+    )
+}
+
+/// Iterates over a field of the headers, which is none where the header is missing, see iter_headers.
+pub fn iter_headers_by_field(
+    source: crate::core::HeaderSource,
+    field: crate::core::HeaderField,
+) -> impl Iterator<Item = Option<Vec<u8>>> {
+    QueryIter::new(
+        move |index, source| match try_load_header_by_field(index, source, field) {
+            Ok(data) => Ok(Some(data)),
+            Err(SysError::ItemMissing) => Ok(None),
+            Err(e) => Err(e),
+        },
+        source,
+    )
+}
+
+pub fn iter_inputs(source: crate::core::Source) -> impl Iterator<Item = crate::core::CellInput> {
+    QueryIter::new(try_load_input, source)
+}
+
+pub fn iter_inputs_by_field(
+    source: crate::core::Source,
+    field: crate::core::InputField,
+) -> impl Iterator<Item = Vec<u8>> {
+    QueryIter::new(move |index, source| try_load_input_by_field(index, source, field), source)
+}
+
+pub fn iter_witnesses(source: crate::core::Source) -> impl Iterator<Item = Vec<u8>> {
+    QueryIter::new(try_load_witness, source)
+}
+
+pub fn load_block_extension(index: u64, source: crate::core::HeaderSource) -> Vec<u8> {
+    try_load_block_extension(index, source).unwrap()
+}
+
+pub fn load_block_extension_into(index: u64, source: crate::core::HeaderSource, buf: &mut [u8]) -> (u64, u64) {
+    try_load_block_extension_into(index, source, buf).unwrap()
+}
+
+pub fn load_block_extension_range(index: u64, source: crate::core::HeaderSource, offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_block_extension_range(index, source, offset, buf).unwrap()
+}
+
+pub fn load_cell(index: u64, source: crate::core::Source) -> crate::core::CellOutput {
+    try_load_cell(index, source).unwrap()
+}
+
+pub fn load_cell_capacity(index: u64, source: crate::core::Source) -> u64 {
+    try_load_cell_capacity(index, source).unwrap()
+}
+
+/// Loads content_size bytes of the cell data starting at content_offset into buf and marks buf as executable. The
+/// buffer must be page aligned and its length a multiple of the page size. Once loaded, the memory is no longer
+/// writable.
+pub fn load_cell_data_as_code(
+    index: u64,
+    source: crate::core::Source,
+    content_offset: u64,
+    content_size: u64,
+    buf: &mut [u8],
+) {
+    try_load_cell_data_as_code(index, source, content_offset, content_size, buf).unwrap()
+}
+
+pub fn load_cell_data_hash(index: u64, source: crate::core::Source) -> [u8; 32] {
+    try_load_cell_data_hash(index, source).unwrap()
+}
+
+pub fn load_cell_into(index: u64, source: crate::core::Source, buf: &mut [u8]) -> (u64, u64) {
+    try_load_cell_into(index, source, buf).unwrap()
+}
+
+pub fn load_cell_lock(index: u64, source: crate::core::Source) -> crate::core::Script {
+    try_load_cell_lock(index, source).unwrap()
+}
+
+pub fn load_cell_lock_hash(index: u64, source: crate::core::Source) -> [u8; 32] {
+    try_load_cell_lock_hash(index, source).unwrap()
+}
+
+pub fn load_cell_occupied_capacity(index: u64, source: crate::core::Source) -> u64 {
+    try_load_cell_occupied_capacity(index, source).unwrap()
+}
+
+pub fn load_cell_range(index: u64, source: crate::core::Source, offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_cell_range(index, source, offset, buf).unwrap()
+}
+
+pub fn load_cell_by_field(index: u64, source: crate::core::Source, field: crate::core::CellField) -> Vec<u8> {
+    try_load_cell_by_field(index, source, field).unwrap()
+}
+
+pub fn load_cell_by_field_into(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::CellField,
+    buf: &mut [u8],
+) -> (u64, u64) {
+    try_load_cell_by_field_into(index, source, field, buf).unwrap()
+}
+
+pub fn load_cell_by_field_range(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::CellField,
+    offset: u64,
+    buf: &mut [u8],
+) -> u64 {
+    try_load_cell_by_field_range(index, source, field, offset, buf).unwrap()
+}
+
+pub fn load_cell_data(index: u64, source: crate::core::Source) -> Vec<u8> {
+    try_load_cell_data(index, source).unwrap()
+}
+
+/// Loads the cell data into buf without allocating. Returns the number of bytes loaded and the full length of the
+/// data; the data was truncated if the second value is larger than the first.
+pub fn load_cell_data_into(index: u64, source: crate::core::Source, buf: &mut [u8]) -> (u64, u64) {
+    try_load_cell_data_into(index, source, buf).unwrap()
+}
+
+/// Loads at most buf.len() bytes of the cell data starting at offset. Returns the number of bytes available from
+/// offset to the end of the data, so a call with an empty buffer simply queries the length.
+pub fn load_cell_data_range(index: u64, source: crate::core::Source, offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_cell_data_range(index, source, offset, buf).unwrap()
+}
+
+pub fn load_cell_type(index: u64, source: crate::core::Source) -> Option<crate::core::Script> {
+    try_load_cell_type(index, source).unwrap()
+}
+
+pub fn load_cell_type_hash(index: u64, source: crate::core::Source) -> Option<[u8; 32]> {
+    try_load_cell_type_hash(index, source).unwrap()
+}
+
+pub fn load_header(index: u64, source: crate::core::HeaderSource) -> crate::core::Header {
+    try_load_header(index, source).unwrap()
+}
+
+pub fn load_header_epoch_length(index: u64, source: crate::core::HeaderSource) -> u64 {
+    try_load_header_epoch_length(index, source).unwrap()
+}
+
+pub fn load_header_epoch_number(index: u64, source: crate::core::HeaderSource) -> u64 {
+    try_load_header_epoch_number(index, source).unwrap()
+}
+
+pub fn load_header_epoch_start_block_number(index: u64, source: crate::core::HeaderSource) -> u64 {
+    try_load_header_epoch_start_block_number(index, source).unwrap()
+}
+
+pub fn load_header_into(index: u64, source: crate::core::HeaderSource, buf: &mut [u8]) -> (u64, u64) {
+    try_load_header_into(index, source, buf).unwrap()
+}
+
+pub fn load_header_range(index: u64, source: crate::core::HeaderSource, offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_header_range(index, source, offset, buf).unwrap()
+}
+
+pub fn load_header_by_field(index: u64, source: crate::core::HeaderSource, field: crate::core::HeaderField) -> Vec<u8> {
+    try_load_header_by_field(index, source, field).unwrap()
+}
+
+pub fn load_header_by_field_into(
+    index: u64,
+    source: crate::core::HeaderSource,
+    field: crate::core::HeaderField,
+    buf: &mut [u8],
+) -> (u64, u64) {
+    try_load_header_by_field_into(index, source, field, buf).unwrap()
+}
+
+pub fn load_header_by_field_range(
+    index: u64,
+    source: crate::core::HeaderSource,
+    field: crate::core::HeaderField,
+    offset: u64,
+    buf: &mut [u8],
+) -> u64 {
+    try_load_header_by_field_range(index, source, field, offset, buf).unwrap()
+}
+
+pub fn load_input(index: u64, source: crate::core::Source) -> crate::core::CellInput {
+    try_load_input(index, source).unwrap()
+}
+
+pub fn load_input_into(index: u64, source: crate::core::Source, buf: &mut [u8]) -> (u64, u64) {
+    try_load_input_into(index, source, buf).unwrap()
+}
+
+pub fn load_input_out_point(index: u64, source: crate::core::Source) -> crate::core::OutPoint {
+    try_load_input_out_point(index, source).unwrap()
+}
+
+pub fn load_input_range(index: u64, source: crate::core::Source, offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_input_range(index, source, offset, buf).unwrap()
+}
+
+pub fn load_input_by_field(index: u64, source: crate::core::Source, field: crate::core::InputField) -> Vec<u8> {
+    try_load_input_by_field(index, source, field).unwrap()
+}
+
+pub fn load_input_by_field_into(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::InputField,
+    buf: &mut [u8],
+) -> (u64, u64) {
+    try_load_input_by_field_into(index, source, field, buf).unwrap()
+}
+
+pub fn load_input_by_field_range(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::InputField,
+    offset: u64,
+    buf: &mut [u8],
+) -> u64 {
+    try_load_input_by_field_range(index, source, field, offset, buf).unwrap()
+}
+
+pub fn load_input_since(index: u64, source: crate::core::Source) -> u64 {
+    try_load_input_since(index, source).unwrap()
+}
+
+pub fn load_script_hash() -> [u8; 32] {
+    try_load_script_hash().unwrap()
+}
+
+pub fn load_script() -> crate::core::Script {
+    try_load_script().unwrap()
+}
+
+pub fn load_script_into(buf: &mut [u8]) -> (u64, u64) {
+    try_load_script_into(buf).unwrap()
+}
+
+pub fn load_script_range(offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_script_range(offset, buf).unwrap()
+}
+
+pub fn load_tx_hash() -> [u8; 32] {
+    try_load_tx_hash().unwrap()
+}
+
+pub fn load_tx() -> crate::core::Transaction {
+    try_load_tx().unwrap()
+}
+
+pub fn load_tx_into(buf: &mut [u8]) -> (u64, u64) {
+    try_load_tx_into(buf).unwrap()
+}
+
+pub fn load_tx_range(offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_tx_range(offset, buf).unwrap()
+}
+
+pub fn load_witness(index: u64, source: crate::core::Source) -> Vec<u8> {
+    try_load_witness(index, source).unwrap()
+}
+
+pub fn load_witness_into(index: u64, source: crate::core::Source, buf: &mut [u8]) -> (u64, u64) {
+    try_load_witness_into(index, source, buf).unwrap()
+}
+
+pub fn load_witness_range(index: u64, source: crate::core::Source, offset: u64, buf: &mut [u8]) -> u64 {
+    try_load_witness_range(index, source, offset, buf).unwrap()
+}
+
+/// Loads an item of any length. The vm is first asked for the length only, then the item is loaded straight into
+/// the spare capacity of a vector, which avoids zeroing a buffer and copying it afterwards.
+fn load_full(a3: u64, a4: u64, a5: u64, id: u64) -> Result<Vec<u8>, SysError> {
+    let len = load_range(&mut [], 0, a3, a4, a5, id)?;
+    let mut out: Vec<u8> = Vec::with_capacity(len as usize);
+    if len == 0 {
+        return Ok(out);
+    }
+    let mut got = len;
+    let ret = ecall(out.as_mut_ptr() as u64, core::ptr::addr_of_mut!(got) as u64, 0, a3, a4, a5, 0, id);
+    SysError::from_code(ret)?;
+    assert!(got == len);
+    unsafe { out.set_len(len as usize) };
+    Ok(out)
+}
+
+/// Loads an item from the beginning into buf. Returns the number of bytes loaded and the full length of the item.
+fn load_into(buf: &mut [u8], a3: u64, a4: u64, a5: u64, id: u64) -> Result<(u64, u64), SysError> {
+    let len = load_range(buf, 0, a3, a4, a5, id)?;
+    Ok((len.min(buf.len() as u64), len))
+}
+
+/// Loads the part of an item starting at offset into buf. Returns the number of bytes available from offset to the
+/// end of the item, which may be larger than the buffer.
+fn load_range(buf: &mut [u8], offset: u64, a3: u64, a4: u64, a5: u64, id: u64) -> Result<u64, SysError> {
+    let mut len: u64 = buf.len() as u64;
+    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, offset, a3, a4, a5, 0, id);
+    SysError::from_code(ret)?;
+    Ok(len)
+}
+
+pub fn pipe() -> [u64; 2] {
+    try_pipe().unwrap()
+}
+
+pub fn process_id() -> u64 {
+    ecall(0, 0, 0, 0, 0, 0, 0, 2603)
+}
+
+pub fn read(fd: u64, buf: &mut [u8]) -> u64 {
+    try_read(fd, buf).unwrap()
+}
+
+pub fn read_all(fd: u64) -> Vec<u8> {
+    try_read_all(fd).unwrap()
+}
+
+pub fn spawn(
+    index: u64,
+    source: crate::core::Source,
+    place: crate::core::Place,
+    bounds: Option<(u32, u32)>,
+    args: &[&str],
+    fds: &[u64],
+) -> u64 {
+    try_spawn(index, source, place, bounds, args, fds).unwrap()
+}
+
+/// Spawns the cell dep found by code hash and hash type, see find_cell_dep.
+pub fn spawn_cell(code_hash: [u8; 32], hash_type: u8, args: &[&str], fds: &[u64]) -> u64 {
+    try_spawn_cell(code_hash, hash_type, args, fds).unwrap()
+}
+
+pub fn try_close(fd: u64) -> Result<(), SysError> {
+    let ret = ecall(fd, 0, 0, 0, 0, 0, 0, 2608);
+    SysError::from_code(ret)
+}
+
+/// Replaces the current script with the program read from the given place. The bounds select a part of it as
+/// (offset, length), where a length of zero means up to the end. It only returns when the vm refuses to execute it.
+pub fn try_exec(
+    index: u64,
+    source: crate::core::Source,
+    place: crate::core::Place,
+    bounds: Option<(u32, u32)>,
+    args: &[&str],
+) -> SysError {
+    let args_vec: Vec<CString> = args.iter().map(|e| CString::new(*e).unwrap()).collect();
+    let args_vec: Vec<u64> = args_vec.iter().map(|e| e.as_bytes_with_nul().as_ptr() as u64).collect();
+    let args_ptr = args_vec.as_ptr() as u64;
+    let ret = ecall(index, source.raw(), place.raw(), encode_bounds(bounds), args.len() as u64, args_ptr, 0, 2043);
+    match SysError::from_code(ret) {
+        Ok(()) => unreachable!("exec returned without an error"),
+        Err(e) => e,
+    }
+}
+
+pub fn try_exec_cell(code_hash: [u8; 32], hash_type: u8, args: &[&str]) -> SysError {
+    match try_find_cell_dep(code_hash, hash_type) {
+        Ok(index) => try_exec(index, crate::core::Source::CellDep, crate::core::Place::CellData, None, args),
+        Err(e) => e,
+    }
+}
+
+/// Reports item missing if no cell dep matches, and wrong format for an unknown hash type.
+pub fn try_find_cell_dep(code_hash: [u8; 32], hash_type: u8) -> Result<u64, SysError> {
+    let mut index = 0;
+    loop {
+        let hash = match hash_type {
+            crate::core::SCRIPT_HASH_TYPE_DATA
+            | crate::core::SCRIPT_HASH_TYPE_DATA1
+            | crate::core::SCRIPT_HASH_TYPE_DATA2 => {
+                try_load_cell_data_hash(index, crate::core::Source::CellDep).map(Some)
+            }
+            crate::core::SCRIPT_HASH_TYPE_TYPE => try_load_cell_type_hash(index, crate::core::Source::CellDep),
+            _ => return Err(SysError::WrongFormat),
+        };
+        match hash {
+            Ok(Some(hash)) if hash == code_hash => return Ok(index),
+            Ok(_) => index += 1,
+            Err(SysError::IndexOutOfBound) => return Err(SysError::ItemMissing),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub fn try_inherited_fds() -> Result<Vec<u64>, SysError> {
     let mut buf = [0; 32];
     let mut len: u64 = 32;
     let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 0, 2607);
-    assert!(ret == 0);
-    buf[..len as usize].to_vec()
-}
-
-pub fn load_block_extension(index: u64, source: u64) -> Vec<u8> {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, 0, 0, 2104);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    buf[..len as usize].to_vec()
-}
-
-pub fn load_cell(index: u64, source: u64) -> crate::core::CellOutput {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, 0, 0, 2071);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    crate::core::CellOutput::molecule_decode(&buf[..len as usize])
-}
-
-pub fn load_cell_by_field(index: u64, source: u64, field: u64) -> Vec<u8> {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, field, 0, 2081);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    buf[..len as usize].to_vec()
-}
-
-pub fn load_cell_data(index: u64, source: u64) -> Vec<u8> {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, 0, 0, 2092);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    buf[..len as usize].to_vec()
-}
-
-pub fn load_header(index: u64, source: u64) -> crate::core::Header {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, 0, 0, 2072);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    crate::core::Header::molecule_decode(&buf[..len as usize])
-}
-
-pub fn load_header_by_field(index: u64, source: u64, field: u64) -> Vec<u8> {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, field, 0, 2082);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    buf[..len as usize].to_vec()
-}
-
-pub fn load_input(index: u64, source: u64) -> crate::core::CellInput {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, 0, 0, 2073);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    crate::core::CellInput::molecule_decode(&buf[..len as usize])
-}
-
-pub fn load_input_by_field(index: u64, source: u64, field: u64) -> Vec<u8> {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, field, 0, 2083);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    buf[..len as usize].to_vec()
+    SysError::from_code(ret)?;
+    Ok(buf[..len as usize].to_vec())
 }
 
-pub fn load_script_hash() -> [u8; 32] {
+pub fn try_load_block_extension(index: u64, source: crate::core::HeaderSource) -> Result<Vec<u8>, SysError> {
+    load_full(index, source.raw(), 0, 2104)
+}
+
+pub fn try_load_block_extension_into(
+    index: u64,
+    source: crate::core::HeaderSource,
+    buf: &mut [u8],
+) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), 0, 2104)
+}
+
+pub fn try_load_block_extension_range(
+    index: u64,
+    source: crate::core::HeaderSource,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), 0, 2104)
+}
+
+pub fn try_load_cell(index: u64, source: crate::core::Source) -> Result<crate::core::CellOutput, SysError> {
+    let buf = load_full(index, source.raw(), 0, 2071)?;
+    Ok(crate::core::CellOutput::molecule_decode(&buf))
+}
+
+pub fn try_load_cell_capacity(index: u64, source: crate::core::Source) -> Result<u64, SysError> {
+    let mut buf = [0; 8];
+    let len = try_load_cell_by_field_into(index, source, crate::core::CellField::Capacity, &mut buf)?;
+    assert!(len == (8, 8));
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn try_load_cell_data_as_code(
+    index: u64,
+    source: crate::core::Source,
+    content_offset: u64,
+    content_size: u64,
+    buf: &mut [u8],
+) -> Result<(), SysError> {
+    assert!((buf.as_ptr() as usize).is_multiple_of(4096));
+    assert!(buf.len().is_multiple_of(4096));
+    let addr = buf.as_mut_ptr() as u64;
+    let ret = ecall(addr, buf.len() as u64, content_offset, content_size, index, source.raw(), 0, 2091);
+    SysError::from_code(ret)
+}
+
+pub fn try_load_cell_data_hash(index: u64, source: crate::core::Source) -> Result<[u8; 32], SysError> {
+    let mut buf = [0; 32];
+    let len = try_load_cell_by_field_into(index, source, crate::core::CellField::DataHash, &mut buf)?;
+    assert!(len == (32, 32));
+    Ok(buf)
+}
+
+pub fn try_load_cell_into(index: u64, source: crate::core::Source, buf: &mut [u8]) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), 0, 2071)
+}
+
+pub fn try_load_cell_lock(index: u64, source: crate::core::Source) -> Result<crate::core::Script, SysError> {
+    let buf = try_load_cell_by_field(index, source, crate::core::CellField::Lock)?;
+    Ok(crate::core::Script::molecule_decode(&buf))
+}
+
+pub fn try_load_cell_lock_hash(index: u64, source: crate::core::Source) -> Result<[u8; 32], SysError> {
+    let mut buf = [0; 32];
+    let len = try_load_cell_by_field_into(index, source, crate::core::CellField::LockHash, &mut buf)?;
+    assert!(len == (32, 32));
+    Ok(buf)
+}
+
+pub fn try_load_cell_occupied_capacity(index: u64, source: crate::core::Source) -> Result<u64, SysError> {
+    let mut buf = [0; 8];
+    let len = try_load_cell_by_field_into(index, source, crate::core::CellField::OccupiedCapacity, &mut buf)?;
+    assert!(len == (8, 8));
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn try_load_cell_range(
+    index: u64,
+    source: crate::core::Source,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), 0, 2071)
+}
+
+pub fn try_load_cell_by_field(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::CellField,
+) -> Result<Vec<u8>, SysError> {
+    load_full(index, source.raw(), field as u64, 2081)
+}
+
+pub fn try_load_cell_by_field_into(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::CellField,
+    buf: &mut [u8],
+) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), field as u64, 2081)
+}
+
+pub fn try_load_cell_by_field_range(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::CellField,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), field as u64, 2081)
+}
+
+pub fn try_load_cell_data(index: u64, source: crate::core::Source) -> Result<Vec<u8>, SysError> {
+    load_full(index, source.raw(), 0, 2092)
+}
+
+pub fn try_load_cell_data_into(
+    index: u64,
+    source: crate::core::Source,
+    buf: &mut [u8],
+) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), 0, 2092)
+}
+
+pub fn try_load_cell_data_range(
+    index: u64,
+    source: crate::core::Source,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), 0, 2092)
+}
+
+pub fn try_load_cell_type(index: u64, source: crate::core::Source) -> Result<Option<crate::core::Script>, SysError> {
+    match try_load_cell_by_field(index, source, crate::core::CellField::Type) {
+        Ok(buf) => Ok(Some(crate::core::Script::molecule_decode(&buf))),
+        Err(SysError::ItemMissing) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn try_load_cell_type_hash(index: u64, source: crate::core::Source) -> Result<Option<[u8; 32]>, SysError> {
+    let mut buf = [0; 32];
+    match try_load_cell_by_field_into(index, source, crate::core::CellField::TypeHash, &mut buf) {
+        Ok(len) => {
+            assert!(len == (32, 32));
+            Ok(Some(buf))
+        }
+        Err(SysError::ItemMissing) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn try_load_header(index: u64, source: crate::core::HeaderSource) -> Result<crate::core::Header, SysError> {
+    let buf = load_full(index, source.raw(), 0, 2072)?;
+    Ok(crate::core::Header::molecule_decode(&buf))
+}
+
+pub fn try_load_header_epoch_length(index: u64, source: crate::core::HeaderSource) -> Result<u64, SysError> {
+    let mut buf = [0; 8];
+    let len = try_load_header_by_field_into(index, source, crate::core::HeaderField::EpochLength, &mut buf)?;
+    assert!(len == (8, 8));
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn try_load_header_epoch_number(index: u64, source: crate::core::HeaderSource) -> Result<u64, SysError> {
+    let mut buf = [0; 8];
+    let len = try_load_header_by_field_into(index, source, crate::core::HeaderField::EpochNumber, &mut buf)?;
+    assert!(len == (8, 8));
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn try_load_header_epoch_start_block_number(
+    index: u64,
+    source: crate::core::HeaderSource,
+) -> Result<u64, SysError> {
+    let mut buf = [0; 8];
+    let len = try_load_header_by_field_into(index, source, crate::core::HeaderField::EpochStartBlockNumber, &mut buf)?;
+    assert!(len == (8, 8));
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn try_load_header_into(
+    index: u64,
+    source: crate::core::HeaderSource,
+    buf: &mut [u8],
+) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), 0, 2072)
+}
+
+pub fn try_load_header_range(
+    index: u64,
+    source: crate::core::HeaderSource,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), 0, 2072)
+}
+
+pub fn try_load_header_by_field(
+    index: u64,
+    source: crate::core::HeaderSource,
+    field: crate::core::HeaderField,
+) -> Result<Vec<u8>, SysError> {
+    load_full(index, source.raw(), field as u64, 2082)
+}
+
+pub fn try_load_header_by_field_into(
+    index: u64,
+    source: crate::core::HeaderSource,
+    field: crate::core::HeaderField,
+    buf: &mut [u8],
+) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), field as u64, 2082)
+}
+
+pub fn try_load_header_by_field_range(
+    index: u64,
+    source: crate::core::HeaderSource,
+    field: crate::core::HeaderField,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), field as u64, 2082)
+}
+
+pub fn try_load_input(index: u64, source: crate::core::Source) -> Result<crate::core::CellInput, SysError> {
+    let buf = load_full(index, source.raw(), 0, 2073)?;
+    Ok(crate::core::CellInput::molecule_decode(&buf))
+}
+
+pub fn try_load_input_into(index: u64, source: crate::core::Source, buf: &mut [u8]) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), 0, 2073)
+}
+
+pub fn try_load_input_out_point(index: u64, source: crate::core::Source) -> Result<crate::core::OutPoint, SysError> {
+    let mut buf = [0; 36];
+    let len = try_load_input_by_field_into(index, source, crate::core::InputField::OutPoint, &mut buf)?;
+    assert!(len == (36, 36));
+    Ok(crate::core::OutPoint::molecule_decode(&buf))
+}
+
+pub fn try_load_input_range(
+    index: u64,
+    source: crate::core::Source,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), 0, 2073)
+}
+
+pub fn try_load_input_by_field(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::InputField,
+) -> Result<Vec<u8>, SysError> {
+    load_full(index, source.raw(), field as u64, 2083)
+}
+
+pub fn try_load_input_by_field_into(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::InputField,
+    buf: &mut [u8],
+) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), field as u64, 2083)
+}
+
+pub fn try_load_input_by_field_range(
+    index: u64,
+    source: crate::core::Source,
+    field: crate::core::InputField,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), field as u64, 2083)
+}
+
+pub fn try_load_input_since(index: u64, source: crate::core::Source) -> Result<u64, SysError> {
+    let mut buf = [0; 8];
+    let len = try_load_input_by_field_into(index, source, crate::core::InputField::Since, &mut buf)?;
+    assert!(len == (8, 8));
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn try_load_script_hash() -> Result<[u8; 32], SysError> {
     let mut buf = [0; 32];
     let mut len: u64 = 32;
     let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 0, 2062);
-    assert!(ret == 0);
-    buf
+    SysError::from_code(ret)?;
+    Ok(buf)
 }
 
-pub fn load_script() -> crate::core::Script {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 0, 2052);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    crate::core::Script::molecule_decode(&buf[..len as usize])
+pub fn try_load_script() -> Result<crate::core::Script, SysError> {
+    let buf = load_full(0, 0, 0, 2052)?;
+    Ok(crate::core::Script::molecule_decode(&buf))
 }
 
-pub fn load_tx_hash() -> [u8; 32] {
+pub fn try_load_script_into(buf: &mut [u8]) -> Result<(u64, u64), SysError> {
+    load_into(buf, 0, 0, 0, 2052)
+}
+
+pub fn try_load_script_range(offset: u64, buf: &mut [u8]) -> Result<u64, SysError> {
+    load_range(buf, offset, 0, 0, 0, 2052)
+}
+
+pub fn try_load_tx_hash() -> Result<[u8; 32], SysError> {
     let mut buf = [0; 32];
     let mut len: u64 = 32;
     let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 0, 2061);
-    assert!(ret == 0);
-    buf
+    SysError::from_code(ret)?;
+    Ok(buf)
 }
 
-pub fn load_tx() -> crate::core::Transaction {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 0, 2051);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    crate::core::Transaction::molecule_decode(&buf[..len as usize])
+pub fn try_load_tx() -> Result<crate::core::Transaction, SysError> {
+    let buf = load_full(0, 0, 0, 2051)?;
+    Ok(crate::core::Transaction::molecule_decode(&buf))
 }
 
-pub fn load_witness(index: u64, source: u64) -> Vec<u8> {
-    let mut buf = [0; 32 * 1024];
-    let mut len: u64 = 32 * 1024;
-    let ret = ecall(buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, index, source, 0, 0, 2074);
-    assert!(ret == 0);
-    assert!(len <= 32 * 1024);
-    buf[..len as usize].to_vec()
+pub fn try_load_tx_into(buf: &mut [u8]) -> Result<(u64, u64), SysError> {
+    load_into(buf, 0, 0, 0, 2051)
 }
 
-pub fn pipe() -> [u64; 2] {
-    let mut fds: [u64; 2] = [0, 0];
-    let ret = ecall(fds.as_mut_ptr() as u64, 0, 0, 0, 0, 0, 0, 2604);
-    assert!(ret == 0);
-    fds
+pub fn try_load_tx_range(offset: u64, buf: &mut [u8]) -> Result<u64, SysError> {
+    load_range(buf, offset, 0, 0, 0, 2051)
 }
 
-pub fn process_id() -> u64 {
-    ecall(0, 0, 0, 0, 0, 0, 0, 2603)
+pub fn try_load_witness(index: u64, source: crate::core::Source) -> Result<Vec<u8>, SysError> {
+    load_full(index, source.raw(), 0, 2074)
 }
 
-pub fn read(fd: u64, buf: &mut [u8]) -> u64 {
+pub fn try_load_witness_into(index: u64, source: crate::core::Source, buf: &mut [u8]) -> Result<(u64, u64), SysError> {
+    load_into(buf, index, source.raw(), 0, 2074)
+}
+
+pub fn try_load_witness_range(
+    index: u64,
+    source: crate::core::Source,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<u64, SysError> {
+    load_range(buf, offset, index, source.raw(), 0, 2074)
+}
+
+pub fn try_pipe() -> Result<[u64; 2], SysError> {
+    let mut fds: [u64; 2] = [0, 0];
+    let ret = ecall(fds.as_mut_ptr() as u64, 0, 0, 0, 0, 0, 0, 2604);
+    SysError::from_code(ret)?;
+    Ok(fds)
+}
+
+pub fn try_read(fd: u64, buf: &mut [u8]) -> Result<u64, SysError> {
     let mut len: u64 = buf.len() as u64;
     let ret = ecall(fd, buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 2606);
-    assert!(ret == 0);
-    len
+    SysError::from_code(ret)?;
+    Ok(len)
 }
 
-pub fn read_all(fd: u64) -> Vec<u8> {
-    let mut out = Vec::new();
-    let mut buf = [0; 256];
+/// Reads from the file descriptor until the other end of the pipe is closed. Data is read straight into the spare
+/// capacity of the result, which doubles whenever it runs out.
+pub fn try_read_all(fd: u64) -> Result<Vec<u8>, SysError> {
+    let mut out: Vec<u8> = Vec::with_capacity(256);
     loop {
-        let mut len = 256;
-        let ret = ecall(fd, buf.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 2606);
-        assert!(ret == 0 || ret == 7);
-        if ret == 0 {
-            out.extend_from_slice(&buf[..len]);
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity());
         }
-        if ret == 7 {
-            break;
+        let spare = out.spare_capacity_mut();
+        let mut len = spare.len() as u64;
+        let ret = ecall(fd, spare.as_mut_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 2606);
+        match SysError::from_code(ret) {
+            Ok(()) => unsafe { out.set_len(out.len() + len as usize) },
+            Err(SysError::OtherEndClosed) => break,
+            Err(e) => return Err(e),
         }
     }
-    out
+    Ok(out)
 }
 
-pub fn spawn(index: u64, source: u64, args: &[&str], fds: &[u64]) -> u64 {
+pub fn try_spawn(
+    index: u64,
+    source: crate::core::Source,
+    place: crate::core::Place,
+    bounds: Option<(u32, u32)>,
+    args: &[&str],
+    fds: &[u64],
+) -> Result<u64, SysError> {
     let args_vec: Vec<CString> = args.iter().map(|e| CString::new(*e).unwrap()).collect();
     let args_vec: Vec<u64> = args_vec.iter().map(|e| e.as_bytes_with_nul().as_ptr() as u64).collect();
     let args_ptr = args_vec.as_ptr() as u64;
@@ -216,25 +1015,38 @@ pub fn spawn(index: u64, source: u64, args: &[&str], fds: &[u64]) -> u64 {
     let mut pid: u64 = 0;
     let spgs = [args.len() as u64, args_ptr, core::ptr::addr_of_mut!(pid) as u64, fdr_ptr];
     let spgs_ptr = spgs.as_ptr() as u64;
-    let ret = ecall(index, source, 0, 0, spgs_ptr, 0, 0, 2601);
-    assert!(ret == 0);
-    pid
+    let ret = ecall(index, source.raw(), place.raw(), encode_bounds(bounds), spgs_ptr, 0, 0, 2601);
+    SysError::from_code(ret)?;
+    Ok(pid)
 }
 
-pub fn vm_version() -> u64 {
-    ecall(0, 0, 0, 0, 0, 0, 0, 2041)
+pub fn try_spawn_cell(code_hash: [u8; 32], hash_type: u8, args: &[&str], fds: &[u64]) -> Result<u64, SysError> {
+    let index = try_find_cell_dep(code_hash, hash_type)?;
+    try_spawn(index, crate::core::Source::CellDep, crate::core::Place::CellData, None, args, fds)
 }
 
-pub fn wait(pid: u64) -> u64 {
+pub fn try_wait(pid: u64) -> Result<u64, SysError> {
     let mut code: u64 = 0;
     let ret = ecall(pid, core::ptr::addr_of_mut!(code) as u64, 0, 0, 0, 0, 0, 2602);
-    assert!(ret == 0);
-    code
+    SysError::from_code(ret)?;
+    Ok(code)
 }
 
-pub fn write(fd: u64, buf: &[u8]) -> u64 {
+pub fn try_write(fd: u64, buf: &[u8]) -> Result<u64, SysError> {
     let mut len: u64 = buf.len() as u64;
     let ret = ecall(fd, buf.as_ptr() as u64, core::ptr::addr_of_mut!(len) as u64, 0, 0, 0, 0, 2605);
-    assert!(ret == 0);
-    len
+    SysError::from_code(ret)?;
+    Ok(len)
+}
+
+pub fn vm_version() -> u64 {
+    ecall(0, 0, 0, 0, 0, 0, 0, 2041)
+}
+
+pub fn wait(pid: u64) -> u64 {
+    try_wait(pid).unwrap()
+}
+
+pub fn write(fd: u64, buf: &[u8]) -> u64 {
+    try_write(fd, buf).unwrap()
 }